@@ -0,0 +1,150 @@
+use std::f32::consts::TAU;
+
+use wasm_bindgen::prelude::*;
+use web_time::Instant;
+
+use crate::{
+    DEFAULT_AZIMUTH, DEFAULT_BACKGROUND, DEFAULT_ELEVATION, DEFAULT_FOV, DEFAULT_ZOOM,
+    display::{self, AccumulateSettings, MAX_SUPERSAMPLE, RenderResolution},
+    number_option, offscreen_display, offscreen_scene, scene,
+    utils::Vec3f,
+};
+
+const BENCH_WIDTH: u32 = 1280;
+const BENCH_HEIGHT: u32 = 720;
+const BENCH_FRAMES: u32 = 120;
+const MAX_BENCH_FRAMES: u32 = 10_000;
+const WARMUP_FRAMES: u32 = 5;
+
+// Waits until the GPU has finished everything submitted so far: the clear is queued after it,
+// so its buffer only maps once the earlier work is done.
+async fn finish_gpu(display: &display::Display) -> Result<(), String> {
+    let buffer = display.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("bench_fence"),
+        size: 4,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = display.device.create_command_encoder(&Default::default());
+    encoder.clear_buffer(&buffer, 0, None);
+    display.queue.submit(Some(encoder.finish()));
+    display::map_read_buffer(&display.device, &buffer).await
+}
+
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    sorted[((sorted.len() - 1) as f64 * fraction).round() as usize]
+}
+
+// Spins the camera once around the scene, drawing one fresh sample per frame the way the viewer
+// does while the camera moves, with no egui, canvas or event loop in the way.
+#[wasm_bindgen]
+pub async fn benchmark(scene_bytes: Vec<u8>, options: JsValue) -> Result<JsValue, JsValue> {
+    let option = |name: &str| number_option(&options, name);
+    let width = option("width")
+        .map_or(BENCH_WIDTH, |width| width as u32)
+        .max(1);
+    let height = option("height")
+        .map_or(BENCH_HEIGHT, |height| height as u32)
+        .max(1);
+    let frames = option("frames")
+        .map_or(BENCH_FRAMES, |frames| frames as u32)
+        .clamp(1, MAX_BENCH_FRAMES);
+    let supersample = option("supersample")
+        .map_or(1, |supersample| supersample as u32)
+        .clamp(1, MAX_SUPERSAMPLE);
+
+    let display = offscreen_display().await?;
+    let resolution = RenderResolution::Native(width, height);
+    if !display.resolution_fits(&resolution) {
+        return Err(format!(
+            "benchmark frame is larger than the gpu's {} pixel limit",
+            display.max_texture_dimension()
+        )
+        .into());
+    }
+    let scene = offscreen_scene(&display, scene_bytes).await?;
+    let supersample = display.clamp_supersample(&resolution, supersample);
+    let mut frame = display.create_render_frame(&resolution, supersample);
+
+    let mut settings = AccumulateSettings {
+        camera: scene::Camera {
+            azimuth: DEFAULT_AZIMUTH,
+            elevation: DEFAULT_ELEVATION,
+            zoom: DEFAULT_ZOOM,
+            fov: DEFAULT_FOV,
+            pan_offset: Vec3f::zeros(),
+            translation: Vec3f::zeros(),
+        },
+        background: DEFAULT_BACKGROUND,
+        mask_mode: false,
+        fixed_seed: false,
+        target_samples: 1,
+        denoise: false,
+        denoise_strength: 0.0,
+        selection: scene::SelectionMode::Highlight,
+        alpha_scale: 1.0,
+        sigma_scale: 1.0,
+        show_grid: false,
+    };
+    let mut accumulated_samples = 0;
+    let mut render = |index: u32| {
+        settings.camera.azimuth = DEFAULT_AZIMUTH + TAU * index as f32 / frames as f32;
+        display.accumulate(
+            &scene,
+            &mut frame,
+            &settings,
+            &mut accumulated_samples,
+            1,
+            true,
+        );
+    };
+
+    // Pipelines and buffers are compiled and uploaded lazily, so keep that out of the timings.
+    for index in 0..WARMUP_FRAMES {
+        render(index);
+    }
+    finish_gpu(&display).await?;
+
+    let start = Instant::now();
+    let mut submit_ms = Vec::with_capacity(frames as usize);
+    for index in 0..frames {
+        let frame_start = Instant::now();
+        render(index);
+        submit_ms.push(frame_start.elapsed().as_secs_f64() * 1000.0);
+    }
+    finish_gpu(&display).await?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+    submit_ms.sort_by(f64::total_cmp);
+
+    let frame_ms = total_ms / frames as f64;
+    web_sys::console::log_1(
+        &format!(
+            "benchmark: {} triangles, {}x{} at {} samples, {} frames: {:.2} ms/frame, \
+             submit median {:.2} ms, p95 {:.2} ms",
+            scene.num_tris(),
+            width,
+            height,
+            display::samples_per_pixel(supersample),
+            frames,
+            frame_ms,
+            percentile(&submit_ms, 0.5),
+            percentile(&submit_ms, 0.95),
+        )
+        .into(),
+    );
+
+    let result = js_sys::Object::new();
+    let set = |name: &str, value: f64| {
+        let _ = js_sys::Reflect::set(&result, &name.into(), &value.into());
+    };
+    set("triangles", scene.num_tris() as f64);
+    set("width", width as f64);
+    set("height", height as f64);
+    set("samples", display::samples_per_pixel(supersample) as f64);
+    set("frames", frames as f64);
+    set("totalMs", total_ms);
+    set("frameMs", frame_ms);
+    set("submitMedianMs", percentile(&submit_ms, 0.5));
+    set("submitP95Ms", percentile(&submit_ms, 0.95));
+    Ok(result.into())
+}
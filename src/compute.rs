@@ -0,0 +1,416 @@
+use std::borrow::Cow;
+
+use wgpu::{Buffer, CommandEncoder, ComputePipeline, Device, util::DeviceExt};
+
+const ITEMS_PER_WORKGROUP: u32 = 512;
+
+pub fn supports_compute(adapter: &wgpu::Adapter) -> bool {
+    adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+}
+
+pub fn create_storage_buffer(device: &Device, count: u32) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("storage_buffer"),
+        size: count.max(1) as u64 * 4,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_SRC
+            | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+pub struct PrefixSum {
+    bind_group_layout: wgpu::BindGroupLayout,
+    scan_pipeline: ComputePipeline,
+    add_pipeline: ComputePipeline,
+    max_workgroups: u32,
+}
+
+impl PrefixSum {
+    pub fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("prefix_sum"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                "shaders/prefix_sum.wgsl"
+            ))),
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("prefix_sum_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, false),
+                storage_entry(3, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("prefix_sum_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            scan_pipeline: create_pipeline("scan_blocks"),
+            add_pipeline: create_pipeline("add_block_offsets"),
+            bind_group_layout,
+            max_workgroups: device.limits().max_compute_workgroups_per_dimension,
+        }
+    }
+
+    // Allocates what scanning the first `count` u32s of `input` into `output` needs, so that
+    // repeated scans of the same buffers encode without creating anything.
+    pub fn plan(
+        &self,
+        device: &Device,
+        input: &Buffer,
+        output: &Buffer,
+        count: u32,
+    ) -> Result<PrefixSumPlan, String> {
+        let num_blocks = count.div_ceil(ITEMS_PER_WORKGROUP).max(1);
+        if num_blocks > self.max_workgroups {
+            return Err(format!("prefix sum over {} items is too large", count));
+        }
+
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("prefix_sum_params"),
+            contents: bytemuck::bytes_of(&[count, 0, 0, 0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let block_sums = create_storage_buffer(device, num_blocks);
+        let bind_group = |block_sums: &Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: input.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: output.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: block_sums.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+
+        let scan_bind_group = bind_group(&block_sums);
+        let carry = if num_blocks > 1 {
+            let block_offsets = create_storage_buffer(device, num_blocks);
+            let inner = self.plan(device, &block_sums, &block_offsets, num_blocks)?;
+            Some((Box::new(inner), bind_group(&block_offsets)))
+        } else {
+            None
+        };
+        Ok(PrefixSumPlan {
+            num_blocks,
+            scan_bind_group,
+            carry,
+        })
+    }
+
+    // Writes the exclusive prefix sum planned for into its output buffer.
+    pub fn encode(&self, encoder: &mut CommandEncoder, plan: &PrefixSumPlan) {
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.scan_pipeline);
+            pass.set_bind_group(0, &plan.scan_bind_group, &[]);
+            pass.dispatch_workgroups(plan.num_blocks, 1, 1);
+        }
+
+        if let Some((inner, add_bind_group)) = &plan.carry {
+            self.encode(encoder, inner);
+
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.add_pipeline);
+            pass.set_bind_group(0, add_bind_group, &[]);
+            pass.dispatch_workgroups(plan.num_blocks, 1, 1);
+        }
+    }
+}
+
+// Bind groups hold on to the buffers they reference, so the plan keeps its scratch space alive.
+pub struct PrefixSumPlan {
+    num_blocks: u32,
+    scan_bind_group: wgpu::BindGroup,
+    // The scan of the block sums, and the pass that adds it back onto each block.
+    carry: Option<(Box<PrefixSumPlan>, wgpu::BindGroup)>,
+}
+
+const RADIX_ITEMS_PER_WORKGROUP: u32 = 256;
+const RADIX_BITS: u32 = 4;
+
+pub struct RadixSort {
+    bind_group_layout: wgpu::BindGroupLayout,
+    count_pipeline: ComputePipeline,
+    scatter_pipeline: ComputePipeline,
+    prefix_sum: PrefixSum,
+    max_workgroups: u32,
+}
+
+impl RadixSort {
+    pub fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("radix_sort"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                "shaders/radix_sort.wgsl"
+            ))),
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("radix_sort_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, true),
+                storage_entry(3, false),
+                storage_entry(4, false),
+                storage_entry(5, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("radix_sort_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            count_pipeline: create_pipeline("count_digits"),
+            scatter_pipeline: create_pipeline("scatter"),
+            bind_group_layout,
+            prefix_sum: PrefixSum::new(device),
+            max_workgroups: device.limits().max_compute_workgroups_per_dimension,
+        }
+    }
+
+    pub fn max_count(&self) -> u32 {
+        self.max_workgroups
+            .saturating_mul(RADIX_ITEMS_PER_WORKGROUP)
+    }
+
+    // Allocates what sorting the first `count` pairs needs, so that repeated sorts of the same
+    // buffers encode without creating anything. Passes ping-pong between the two buffers of each
+    // pair; their number is even, so the result ends up back in `keys[0]` and `values[0]`.
+    pub fn plan(
+        &self,
+        device: &Device,
+        keys: [&Buffer; 2],
+        values: [&Buffer; 2],
+        count: u32,
+    ) -> Result<RadixSortPlan, String> {
+        let num_blocks = count.div_ceil(RADIX_ITEMS_PER_WORKGROUP).max(1);
+        if num_blocks > self.max_workgroups {
+            return Err(format!("radix sort over {} items is too large", count));
+        }
+
+        let num_digits = num_blocks << RADIX_BITS;
+        let block_counts = create_storage_buffer(device, num_digits);
+        let block_offsets = create_storage_buffer(device, num_digits);
+        let prefix_sum = self
+            .prefix_sum
+            .plan(device, &block_counts, &block_offsets, num_digits)?;
+        let passes = (0..32 / RADIX_BITS)
+            .map(|pass| {
+                let (from, to) = ((pass % 2) as usize, ((pass + 1) % 2) as usize);
+                let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("radix_sort_params"),
+                    contents: bytemuck::bytes_of(&[count, pass * RADIX_BITS, num_blocks, 0]),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+                let bind_group = |block_digits: &Buffer| {
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: None,
+                        layout: &self.bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: params.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: keys[from].as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: values[from].as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: keys[to].as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 4,
+                                resource: values[to].as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 5,
+                                resource: block_digits.as_entire_binding(),
+                            },
+                        ],
+                    })
+                };
+                (bind_group(&block_counts), bind_group(&block_offsets))
+            })
+            .collect();
+        Ok(RadixSortPlan {
+            num_blocks,
+            prefix_sum,
+            passes,
+        })
+    }
+
+    // Sorts the planned pairs by ascending key.
+    pub fn encode(&self, encoder: &mut CommandEncoder, plan: &RadixSortPlan) {
+        for (count_bind_group, scatter_bind_group) in &plan.passes {
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                pass.set_pipeline(&self.count_pipeline);
+                pass.set_bind_group(0, count_bind_group, &[]);
+                pass.dispatch_workgroups(plan.num_blocks, 1, 1);
+            }
+            self.prefix_sum.encode(encoder, &plan.prefix_sum);
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.scatter_pipeline);
+            pass.set_bind_group(0, scatter_bind_group, &[]);
+            pass.dispatch_workgroups(plan.num_blocks, 1, 1);
+        }
+    }
+}
+
+pub struct RadixSortPlan {
+    num_blocks: u32,
+    prefix_sum: PrefixSumPlan,
+    // Bind groups for counting and then scattering each digit.
+    passes: Vec<(wgpu::BindGroup, wgpu::BindGroup)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::display::map_read_buffer;
+
+    // Compares a multi-level GPU scan against a CPU reference. It needs WebGPU, so it passes
+    // without checking anything where there is none, such as under node.
+    #[wasm_bindgen_test]
+    async fn prefix_sum_matches_cpu_reference() {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::BROWSER_WEBGPU,
+            ..Default::default()
+        });
+        let Ok(adapter) = instance.request_adapter(&Default::default()).await else {
+            return;
+        };
+        if !supports_compute(&adapter) {
+            return;
+        }
+        let (device, queue) = adapter.request_device(&Default::default()).await.unwrap();
+
+        let count = 3 * ITEMS_PER_WORKGROUP * ITEMS_PER_WORKGROUP + 17;
+        let values = (0..count).map(|i| (i * 7919) % 5).collect::<Vec<u32>>();
+        let expected = values
+            .iter()
+            .scan(0u32, |sum, value| {
+                let prefix = *sum;
+                *sum += value;
+                Some(prefix)
+            })
+            .collect::<Vec<_>>();
+
+        let input = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("prefix_sum_test_input"),
+            contents: bytemuck::cast_slice(&values),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let output = create_storage_buffer(&device, count);
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("prefix_sum_test_readback"),
+            size: output.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let prefix_sum = PrefixSum::new(&device);
+        let plan = prefix_sum.plan(&device, &input, &output, count).unwrap();
+        let mut encoder = device.create_command_encoder(&Default::default());
+        prefix_sum.encode(&mut encoder, &plan);
+        encoder.copy_buffer_to_buffer(&output, 0, &readback, 0, output.size());
+        queue.submit(Some(encoder.finish()));
+
+        map_read_buffer(&device, &readback).await.unwrap();
+        let actual =
+            bytemuck::cast_slice::<u8, u32>(&readback.slice(..).get_mapped_range()).to_vec();
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,17 +1,36 @@
 use crevice::std140::{AsStd140, Std140};
+use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, cell::RefCell, fmt::Formatter};
 
+use web_time::Instant;
 use wgpu::{
     Adapter, BindGroup, Color, Device, Queue, RenderPipeline, Surface, Texture, TextureFormat,
     util::DeviceExt,
 };
 
-use crate::{AppState, gui::GuiRenderData, utils::Vec4u};
+use crate::{
+    AppState,
+    gui::GuiRenderData,
+    scene::{Camera, DrawSettings, Scene, SelectionMode, Z_FAR, Z_NEAR},
+    utils::{Vec4u, yield_async},
+};
 
 pub const FRAME_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
 pub const FRAME_FORMAT_FLOAT: TextureFormat = TextureFormat::Rgba32Float;
+pub const FRAME_FORMAT_HALF: TextureFormat = TextureFormat::Rgba16Float;
+
+// Supersample is a per-axis scale, so each displayed pixel averages its square in samples and the
+// sample targets grow with that square: 25 samples at 2160p would be a 19200×10800 frame.
+pub const MAX_SUPERSAMPLE: u32 = 5;
+// Color plus depth per sample, against a budget that keeps the targets within reach of most GPUs.
+const SAMPLE_TEXEL_BYTES: u64 = 8;
+const MAX_SAMPLE_BYTES: u64 = 512 << 20;
+
+pub fn samples_per_pixel(supersample: u32) -> u32 {
+    supersample * supersample
+}
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RenderResolution {
     Ws360P,
     Ws720P,
@@ -19,30 +38,51 @@ pub enum RenderResolution {
     Ws1440P,
     Ws2160P,
     Native(u32, u32),
+    Auto(u32, u32),
 }
 
 impl RenderResolution {
-    fn width(&self) -> u32 {
+    pub fn width(&self) -> u32 {
         match self {
             RenderResolution::Ws360P => 640,
             RenderResolution::Ws720P => 1280,
             RenderResolution::Ws1080P => 1920,
             RenderResolution::Ws1440P => 2560,
             RenderResolution::Ws2160P => 3840,
-            RenderResolution::Native(w, _) => *w,
+            RenderResolution::Native(w, _) | RenderResolution::Auto(w, _) => *w,
         }
     }
 
-    fn height(&self) -> u32 {
+    pub fn height(&self) -> u32 {
         match self {
             RenderResolution::Ws360P => 360,
             RenderResolution::Ws720P => 720,
             RenderResolution::Ws1080P => 1080,
             RenderResolution::Ws1440P => 1440,
             RenderResolution::Ws2160P => 2160,
-            RenderResolution::Native(_, h) => *h,
+            RenderResolution::Native(_, h) | RenderResolution::Auto(_, h) => *h,
         }
     }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width() as f32 / self.height() as f32
+    }
+
+    pub fn lower(&self) -> Option<RenderResolution> {
+        [
+            RenderResolution::Ws2160P,
+            RenderResolution::Ws1440P,
+            RenderResolution::Ws1080P,
+            RenderResolution::Ws720P,
+            RenderResolution::Ws360P,
+        ]
+        .into_iter()
+        .find(|preset| preset.height() < self.height())
+    }
 }
 
 impl std::fmt::Display for RenderResolution {
@@ -54,11 +94,85 @@ impl std::fmt::Display for RenderResolution {
             RenderResolution::Ws1440P => write!(f, "1440p"),
             RenderResolution::Ws2160P => write!(f, "2160p"),
             RenderResolution::Native(w, h) => write!(f, "{}x{}", w, h),
+            RenderResolution::Auto(w, h) => write!(f, "auto {}x{}", w, h),
         }
     }
 }
 
+pub fn texture_size_bytes(texture: &Texture) -> u64 {
+    let texel_bytes = texture.format().block_copy_size(None).unwrap_or(0) as u64;
+    texture.width() as u64 * texture.height() as u64 * texel_bytes
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UpscaleFilter {
+    Nearest,
+    Bilinear,
+    Sharpen,
+}
+
+impl std::fmt::Display for UpscaleFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpscaleFilter::Nearest => write!(f, "nearest"),
+            UpscaleFilter::Bilinear => write!(f, "bilinear"),
+            UpscaleFilter::Sharpen => write!(f, "sharpen"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tonemap {
+    None,
+    Reinhard,
+    Aces,
+}
+
+impl std::fmt::Display for Tonemap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tonemap::None => write!(f, "none"),
+            Tonemap::Reinhard => write!(f, "reinhard"),
+            Tonemap::Aces => write!(f, "aces"),
+        }
+    }
+}
+
+#[derive(AsStd140)]
+struct BlitGlobals {
+    exposure: f32,
+    srgb_encode: u32,
+    upscale_filter: u32,
+    dither_seed: u32,
+    tonemap: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Color,
+    Depth,
+}
+
+impl std::fmt::Display for ViewMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViewMode::Color => write!(f, "color"),
+            ViewMode::Depth => write!(f, "depth"),
+        }
+    }
+}
+
+#[derive(AsStd140)]
+struct PlaceholderGlobals {
+    background: mint::Vector3<f32>,
+    time: f32,
+    aspect: f32,
+}
+
 pub struct RenderFrame {
+    pub gpu_bytes: u64,
+    pub resolution: RenderResolution,
+    pub supersample: u32,
     sample_texture: Texture,
     blit_front_texture: Texture,
     blit_back_texture: Texture,
@@ -67,6 +181,140 @@ pub struct RenderFrame {
     sample_bind_group_back: BindGroup,
     blit_front_bind_group: BindGroup,
     blit_back_bind_group: BindGroup,
+    depth_view_bind_group: BindGroup,
+}
+
+impl RenderFrame {
+    // Frames with the same sample dimensions can be reused when only the resolution label changed.
+    pub fn fits(&self, resolution: &RenderResolution, supersample: u32) -> bool {
+        self.supersample == supersample
+            && self.resolution.width() == resolution.width()
+            && self.resolution.height() == resolution.height()
+    }
+}
+
+pub async fn map_read_buffer(device: &Device, buffer: &wgpu::Buffer) -> Result<(), String> {
+    let (sender, mut receiver) = futures::channel::oneshot::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+    let result = loop {
+        let _ = device.poll(wgpu::PollType::Poll);
+        match receiver.try_recv() {
+            Ok(Some(result)) => break result,
+            Ok(None) => yield_async(10).await,
+            Err(_) => return Err("buffer mapping was cancelled".to_string()),
+        }
+    };
+    result.map_err(|e| e.to_string())
+}
+
+// The letterboxed rectangle the frame is shown in, as x, y, width, height in canvas pixels.
+pub fn frame_viewport(frame_aspect: f32, canvas_width: u32, canvas_height: u32) -> [f32; 4] {
+    let canvas_aspect = canvas_width as f32 / canvas_height as f32;
+    if frame_aspect > canvas_aspect {
+        let box_width = canvas_width as f32;
+        let box_height = canvas_width as f32 / frame_aspect;
+        let border = (canvas_height as f32 - box_height) / 2.0;
+        [0.0, border, box_width, box_height]
+    } else {
+        let box_width = canvas_height as f32 * frame_aspect;
+        let box_height = canvas_height as f32;
+        let border = (canvas_width as f32 - box_width) / 2.0;
+        [border, 0.0, box_width, box_height]
+    }
+}
+
+pub struct FramePixels {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<[f32; 4]>,
+}
+
+// Counts from the last reset, so a fixed-seed frame depends on the view and not on its history.
+fn sample_index(accumulated_samples: u32, stale_camera: bool) -> u32 {
+    if stale_camera { 0 } else { accumulated_samples }
+}
+
+// What the splat and downsample passes read, so renders without an AppState can share them.
+pub struct AccumulateSettings {
+    pub camera: Camera,
+    pub background: [f32; 3],
+    pub mask_mode: bool,
+    pub fixed_seed: bool,
+    pub target_samples: u32,
+    pub denoise: bool,
+    pub denoise_strength: f32,
+    pub selection: SelectionMode,
+    pub alpha_scale: f32,
+    pub sigma_scale: f32,
+    pub show_grid: bool,
+}
+
+// Browsers don't list adapters; each request is answered with the one they pick for it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AdapterRequest {
+    pub power_preference: wgpu::PowerPreference,
+    pub force_fallback_adapter: bool,
+}
+
+impl Default for AdapterRequest {
+    fn default() -> Self {
+        Self {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+        }
+    }
+}
+
+// The distinct WebGPU adapters the browser hands out, found by making every kind of request.
+// WebGL has one context per canvas and so nothing to choose between.
+pub async fn enumerate_adapters() -> Vec<(AdapterRequest, wgpu::AdapterInfo)> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::BROWSER_WEBGPU,
+        ..Default::default()
+    });
+    let mut adapters: Vec<(AdapterRequest, wgpu::AdapterInfo)> = Vec::new();
+    for power_preference in [
+        wgpu::PowerPreference::HighPerformance,
+        wgpu::PowerPreference::LowPower,
+    ] {
+        for force_fallback_adapter in [false, true] {
+            let options = wgpu::RequestAdapterOptions {
+                power_preference,
+                force_fallback_adapter,
+                compatible_surface: None,
+            };
+            let Ok(adapter) = instance.request_adapter(&options).await else {
+                continue;
+            };
+            let info = adapter.get_info();
+            if !adapters.iter().any(|(_, known)| *known == info) {
+                let request = AdapterRequest {
+                    power_preference,
+                    force_fallback_adapter,
+                };
+                adapters.push((request, info));
+            }
+        }
+    }
+    adapters
+}
+
+// Browsers often leave the name empty to avoid fingerprinting.
+pub fn adapter_label(info: &wgpu::AdapterInfo) -> String {
+    if !info.name.is_empty() {
+        return info.name.clone();
+    }
+    match info.device_type {
+        wgpu::DeviceType::DiscreteGpu => "discrete gpu",
+        wgpu::DeviceType::IntegratedGpu => "integrated gpu",
+        wgpu::DeviceType::Cpu => "software",
+        _ => "gpu",
+    }
+    .to_string()
 }
 
 pub struct Display {
@@ -76,14 +324,49 @@ pub struct Display {
     pub queue: Queue,
     downsample_pipeline: RenderPipeline,
     blit_pipeline: RenderPipeline,
+    placeholder_pipeline: RenderPipeline,
+    depth_view_pipeline: RenderPipeline,
+    depth_view_planes_buffer: wgpu::Buffer,
+    placeholder_uniform_buffer: wgpu::Buffer,
+    placeholder_bind_group: wgpu::BindGroup,
+    created_at: Instant,
     ui_renderer: RefCell<egui_wgpu::Renderer>,
     pub backend: wgpu::Backend,
+    pub accumulation_format: TextureFormat,
     uniform_bind_group: wgpu::BindGroup,
     uniform_buffer: wgpu::Buffer,
+    blit_uniform_bind_group: wgpu::BindGroup,
+    blit_uniform_buffer: wgpu::Buffer,
 }
 
 impl Display {
-    pub async fn from_canvas(canvas: &web_sys::HtmlCanvasElement) -> Self {
+    pub async fn from_canvas(
+        canvas: &web_sys::HtmlCanvasElement,
+        request: AdapterRequest,
+    ) -> Result<Self, String> {
+        Self::new(
+            wgpu::SurfaceTarget::Canvas(canvas.clone()),
+            canvas.width().max(1),
+            canvas.height().max(1),
+            request,
+        )
+        .await
+    }
+
+    // For renders that are read back rather than shown. WebGL creates its context from a surface,
+    // so an unattached offscreen canvas stands in for one; frames only reach the RenderFrame.
+    pub async fn offscreen(request: AdapterRequest) -> Result<Self, String> {
+        let canvas = web_sys::OffscreenCanvas::new(1, 1)
+            .map_err(|_| "could not create offscreen canvas".to_string())?;
+        Self::new(wgpu::SurfaceTarget::OffscreenCanvas(canvas), 1, 1, request).await
+    }
+
+    async fn new(
+        target: wgpu::SurfaceTarget<'static>,
+        width: u32,
+        height: u32,
+        request: AdapterRequest,
+    ) -> Result<Self, String> {
         crate::utils::yield_async(10).await;
 
         let descriptor = wgpu::InstanceDescriptor {
@@ -93,17 +376,17 @@ impl Display {
         };
         let instance = wgpu::util::new_instance_with_webgpu_detection(&descriptor).await;
         let surface = instance
-            .create_surface(wgpu::SurfaceTarget::Canvas(canvas.clone()))
-            .unwrap();
+            .create_surface(target)
+            .map_err(|err| format!("could not create a surface: {}", err))?;
         let adapter_options = wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            force_fallback_adapter: false,
+            power_preference: request.power_preference,
+            force_fallback_adapter: request.force_fallback_adapter,
             compatible_surface: Some(&surface),
         };
         let adapter = instance
             .request_adapter(&adapter_options)
             .await
-            .expect("adapter supports WebGPU or WebGL");
+            .map_err(|err| format!("no WebGPU or WebGL adapter available: {}", err))?;
 
         let limits = adapter.limits();
         web_sys::console::log_1(&format!("Adapter limits: {:?}", limits).into());
@@ -111,6 +394,18 @@ impl Display {
         web_sys::console::log_1(&format!("Adapter info: {:?}", info).into());
         let backend = info.backend;
 
+        // WebGL often can't render to 32-bit float targets, which silently breaks accumulation.
+        let accumulation_format = if adapter
+            .get_texture_format_features(FRAME_FORMAT_FLOAT)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        {
+            FRAME_FORMAT_FLOAT
+        } else {
+            FRAME_FORMAT_HALF
+        };
+        web_sys::console::log_1(&format!("Accumulation format: {:?}", accumulation_format).into());
+
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
                 label: None,
@@ -120,11 +415,13 @@ impl Display {
                 trace: wgpu::Trace::Off,
             })
             .await
-            .expect("adapter supports WebGPU");
+            .map_err(|err| format!("could not create a gpu device: {}", err))?;
 
         let mut surface_config = surface
-            .get_default_config(&adapter, 512, 512)
-            .expect("adapter supports config");
+            .get_default_config(&adapter, width, height)
+            .ok_or("the gpu adapter can't present to this canvas".to_string())?;
+        // The surface never encodes: blit.wgsl owns the optional linear-to-sRGB step, so the
+        // trained colors, which are already display-referred, reach the screen unchanged by default.
         surface_config.format = surface_config.format.remove_srgb_suffix();
         web_sys::console::log_1(&format!("Surface config: {:?}", surface_config).into());
         surface.configure(&device, &surface_config);
@@ -233,7 +530,7 @@ impl Display {
                 module: &downsample_shader,
                 entry_point: Some("fs_main"),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: FRAME_FORMAT_FLOAT,
+                    format: accumulation_format,
                     blend: None, //Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -276,9 +573,52 @@ impl Display {
                     },
                 ],
             });
+        let blit_globals = BlitGlobals {
+            exposure: 1.0,
+            srgb_encode: 0,
+            upscale_filter: 0,
+            dither_seed: 0,
+            tonemap: 0,
+        };
+        let blit_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: blit_globals.as_std140().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let blit_uniform_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("blit_uniform_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(
+                            (BlitGlobals::std140_size_static() as u64)
+                                .try_into()
+                                .unwrap(),
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+        let blit_uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("blit_uniform_bind_group"),
+            layout: &blit_uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &blit_uniform_buffer,
+                    offset: 0,
+                    size: None,
+                }),
+            }],
+        });
+
         let blit_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[&blit_bind_group_layout],
+            bind_group_layouts: &[&blit_bind_group_layout, &blit_uniform_bind_group_layout],
             push_constant_ranges: &[],
         });
         let blit_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -307,18 +647,209 @@ impl Display {
             multisample: Default::default(),
         });
 
-        Self {
+        let placeholder_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("placeholder"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                "shaders/placeholder.wgsl"
+            ))),
+        });
+        let placeholder_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("placeholder_uniform_buffer"),
+            size: PlaceholderGlobals::std140_size_static() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let placeholder_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("placeholder_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let placeholder_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("placeholder_bind_group"),
+            layout: &placeholder_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: placeholder_uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let placeholder_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&placeholder_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let placeholder_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("placeholder_pipeline"),
+            layout: Some(&placeholder_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &placeholder_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &placeholder_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(surface_config.format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multiview: None,
+            cache: None,
+            multisample: Default::default(),
+        });
+
+        let depth_view_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("depth_view"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                "shaders/depth_view.wgsl"
+            ))),
+        });
+        let depth_view_planes_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("depth_view_planes"),
+                contents: bytemuck::cast_slice(&[Z_NEAR, Z_FAR, 0.0, 0.0]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let depth_view_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("depth_view_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let depth_view_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&depth_view_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let depth_view_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("depth_view_pipeline"),
+            layout: Some(&depth_view_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &depth_view_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &depth_view_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(surface_config.format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multiview: None,
+            cache: None,
+            multisample: Default::default(),
+        });
+
+        Ok(Self {
             surface,
             adapter,
             device,
             queue,
             downsample_pipeline,
             blit_pipeline,
+            placeholder_pipeline,
+            depth_view_pipeline,
+            depth_view_planes_buffer,
+            placeholder_uniform_buffer,
+            placeholder_bind_group,
+            created_at: Instant::now(),
             ui_renderer,
             backend,
+            accumulation_format,
             uniform_bind_group,
             uniform_buffer,
+            blit_uniform_bind_group,
+            blit_uniform_buffer,
+        })
+    }
+
+    pub fn max_texture_dimension(&self) -> u32 {
+        self.device.limits().max_texture_dimension_2d
+    }
+
+    pub fn resolution_fits(&self, resolution: &RenderResolution) -> bool {
+        let max_dimension = self.max_texture_dimension();
+        resolution.width() <= max_dimension && resolution.height() <= max_dimension
+    }
+
+    // Canvas-following resolutions shrink to fit and keep their aspect, anything else drops to the
+    // largest preset that fits.
+    pub fn fit_resolution(&self, resolution: &RenderResolution) -> RenderResolution {
+        if self.resolution_fits(resolution) {
+            return resolution.clone();
+        }
+        if let RenderResolution::Auto(width, height) = *resolution {
+            let scale = self.max_texture_dimension() as f64 / width.max(height) as f64;
+            return RenderResolution::Auto(
+                ((width as f64 * scale) as u32).max(1),
+                ((height as f64 * scale) as u32).max(1),
+            );
+        }
+        let mut fitted = resolution.clone();
+        while let Some(lower) = fitted.lower() {
+            fitted = lower;
+            if self.resolution_fits(&fitted) {
+                break;
+            }
         }
+        fitted
+    }
+
+    pub fn clamp_supersample(&self, resolution: &RenderResolution, supersample: u32) -> u32 {
+        let max_dimension = self.max_texture_dimension();
+        let max_supersample = max_dimension / resolution.width().max(resolution.height());
+        let pixels = resolution.width() as u64 * resolution.height() as u64;
+        let mut supersample = supersample.min(max_supersample.min(MAX_SUPERSAMPLE)).max(1);
+        while supersample > 1
+            && pixels * samples_per_pixel(supersample) as u64 * SAMPLE_TEXEL_BYTES
+                > MAX_SAMPLE_BYTES
+        {
+            supersample -= 1;
+        }
+        supersample
     }
 
     pub fn create_render_frame(
@@ -326,6 +857,9 @@ impl Display {
         resolution: &RenderResolution,
         supersample: u32,
     ) -> RenderFrame {
+        // Callers fit the resolution and supersample first; this only keeps wgpu from panicking.
+        let resolution = &self.fit_resolution(resolution);
+        let supersample = self.clamp_supersample(resolution, supersample);
         let width = resolution.width();
         let height = resolution.height();
 
@@ -354,7 +888,7 @@ impl Display {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
         });
         let blit_front_texture = self.device.create_texture(&wgpu::TextureDescriptor {
@@ -367,9 +901,11 @@ impl Display {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: FRAME_FORMAT_FLOAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[FRAME_FORMAT_FLOAT],
+            format: self.accumulation_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[self.accumulation_format],
         });
         let blit_back_texture = self.device.create_texture(&wgpu::TextureDescriptor {
             label: None,
@@ -381,13 +917,15 @@ impl Display {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: FRAME_FORMAT_FLOAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[FRAME_FORMAT_FLOAT],
+            format: self.accumulation_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[self.accumulation_format],
         });
 
         let mut blit_view_desc = wgpu::TextureViewDescriptor::default();
-        blit_view_desc.format = Some(FRAME_FORMAT_FLOAT);
+        blit_view_desc.format = Some(self.accumulation_format);
 
         let blit_sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
             label: None,
@@ -479,7 +1017,31 @@ impl Display {
             ],
         });
 
+        let depth_view = depth_texture.create_view(&Default::default());
+        let depth_view_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth_view_bind_group"),
+            layout: &self.depth_view_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.depth_view_planes_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let gpu_bytes = texture_size_bytes(&sample_texture)
+            + texture_size_bytes(&depth_texture)
+            + texture_size_bytes(&blit_front_texture)
+            + texture_size_bytes(&blit_back_texture);
+
         RenderFrame {
+            gpu_bytes,
+            resolution: resolution.clone(),
+            supersample,
             sample_texture,
             blit_front_texture,
             blit_back_texture,
@@ -488,9 +1050,229 @@ impl Display {
             sample_bind_group_back,
             blit_front_bind_group,
             blit_back_bind_group,
+            depth_view_bind_group,
+        }
+    }
+
+    pub fn read_frame(
+        &self,
+        frame: &RenderFrame,
+    ) -> impl Future<Output = Result<FramePixels, String>> + 'static {
+        let texture = &frame.blit_front_texture;
+        let width = texture.width();
+        let height = texture.height();
+        let format = self.accumulation_format;
+        let texel_bytes = format.block_copy_size(None).unwrap();
+        let unpadded_row_bytes = width * texel_bytes;
+        let padded_row_bytes = unpadded_row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback_buffer"),
+            size: (padded_row_bytes * height) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let device = self.device.clone();
+
+        async move {
+            map_read_buffer(&device, &buffer)
+                .await
+                .map_err(|e| format!("could not read back frame: {e}"))?;
+
+            let data = buffer.slice(..).get_mapped_range();
+            let mut rgba = Vec::with_capacity((width * height) as usize);
+            for row in data.chunks(padded_row_bytes as usize) {
+                for texel in row[..unpadded_row_bytes as usize].chunks(texel_bytes as usize) {
+                    rgba.push(if format == FRAME_FORMAT_HALF {
+                        bytemuck::pod_read_unaligned::<[half::f16; 4]>(texel).map(half::f16::to_f32)
+                    } else {
+                        bytemuck::pod_read_unaligned::<[f32; 4]>(texel)
+                    });
+                }
+            }
+            std::mem::drop(data);
+            buffer.unmap();
+
+            Ok(FramePixels {
+                width,
+                height,
+                rgba,
+            })
         }
     }
 
+    // Renders subframe_count samples into the frame's accumulation textures, stopping early once
+    // a fixed-seed view has converged.
+    pub fn accumulate(
+        &self,
+        scene: &Scene,
+        frame: &mut RenderFrame,
+        settings: &AccumulateSettings,
+        accumulated_samples: &mut u32,
+        subframe_count: u32,
+        stale_camera: bool,
+    ) {
+        let sample_view = frame
+            .sample_texture
+            .create_view(&wgpu::TextureViewDescriptor {
+                format: Some(FRAME_FORMAT),
+                ..Default::default()
+            });
+        let depth_view = frame
+            .depth_texture
+            .create_view(&wgpu::TextureViewDescriptor {
+                format: Some(wgpu::TextureFormat::Depth32Float),
+                ..Default::default()
+            });
+
+        for subframe in 0..subframe_count {
+            // A fixed-seed frame is a deterministic function of the camera once converged.
+            if settings.fixed_seed
+                && !stale_camera
+                && *accumulated_samples >= settings.target_samples
+            {
+                break;
+            }
+            let sample_index = sample_index(*accumulated_samples, stale_camera);
+            *accumulated_samples = sample_index + 1;
+            let fixed_seed = settings.fixed_seed.then_some(sample_index);
+            // The mask is coverage over black, whatever the background.
+            let background = if settings.mask_mode {
+                [0.0; 3]
+            } else {
+                settings.background
+            };
+
+            std::mem::swap(&mut frame.blit_front_texture, &mut frame.blit_back_texture);
+            std::mem::swap(
+                &mut frame.blit_front_bind_group,
+                &mut frame.blit_back_bind_group,
+            );
+            std::mem::swap(
+                &mut frame.sample_bind_group_front,
+                &mut frame.sample_bind_group_back,
+            );
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+            if subframe == 0 {
+                let (camera, aspect) = (&settings.camera, frame.resolution.aspect());
+                scene.cull(&self.queue, camera, aspect, stale_camera);
+                scene.encode_sort(&self.queue, &mut encoder, camera, aspect, stale_camera);
+            }
+
+            let mut splat_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &sample_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(Color {
+                            r: background[0] as f64,
+                            g: background[1] as f64,
+                            b: background[2] as f64,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            let draw_settings = DrawSettings {
+                width: (frame.resolution.width() * frame.supersample) as i32,
+                height: (frame.resolution.height() * frame.supersample) as i32,
+                supersample: frame.supersample,
+                subframe,
+                mask: settings.mask_mode,
+                fixed_seed,
+                selection: settings.selection,
+                alpha_scale: settings.alpha_scale,
+                sigma_scale: settings.sigma_scale,
+            };
+            let camera = &settings.camera;
+            scene.draw(&self.queue, &mut splat_render_pass, camera, &draw_settings);
+            // Left out of the mask, which is the scene's coverage alone.
+            if settings.show_grid && !settings.mask_mode {
+                scene.draw_grid(&self.queue, &mut splat_render_pass, camera, &draw_settings);
+            }
+
+            std::mem::drop(splat_render_pass);
+
+            let blit_view = frame
+                .blit_front_texture
+                .create_view(&wgpu::TextureViewDescriptor {
+                    format: Some(self.accumulation_format),
+                    ..Default::default()
+                });
+
+            let mut downsample_render_pass =
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &blit_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            let supersample_vec = Vec4u::new(
+                frame.supersample,
+                stale_camera as u32,
+                (settings.denoise && !settings.fixed_seed) as u32,
+                settings.denoise_strength.to_bits(),
+            );
+            let supersample_vec: mint::Vector4<u32> = supersample_vec.into();
+            self.queue.write_buffer(
+                &self.uniform_buffer,
+                0,
+                supersample_vec.as_std140().as_bytes(),
+            );
+            downsample_render_pass.set_pipeline(&self.downsample_pipeline);
+            downsample_render_pass.set_bind_group(0, &frame.sample_bind_group_front, &[]);
+            downsample_render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+            downsample_render_pass.draw(0..4, 0..1);
+
+            std::mem::drop(downsample_render_pass);
+
+            self.queue.submit(Some(encoder.finish()));
+            scene.read_back();
+        }
+    }
+
+    // Returns whether this was the first frame presented with the current scene.
     pub fn render(
         &self,
         gui_render_data: GuiRenderData,
@@ -499,124 +1281,32 @@ impl Display {
         canvas_height: u32,
         subframe_count: u32,
         stale_camera: bool,
-    ) {
+    ) -> bool {
+        if let Some(scene) = &mut state.scene {
+            scene.set_depth_bias(&self.device, state.depth_bias);
+        }
         if let Some(scene) = &state.scene {
-            let sample_view =
-                state
-                    .render_frame
-                    .sample_texture
-                    .create_view(&wgpu::TextureViewDescriptor {
-                        format: Some(FRAME_FORMAT),
-                        ..Default::default()
-                    });
-            let depth_view =
-                state
-                    .render_frame
-                    .depth_texture
-                    .create_view(&wgpu::TextureViewDescriptor {
-                        format: Some(wgpu::TextureFormat::Depth32Float),
-                        ..Default::default()
-                    });
-
-            for _i in 0..subframe_count {
-                std::mem::swap(
-                    &mut state.render_frame.blit_front_texture,
-                    &mut state.render_frame.blit_back_texture,
-                );
-                std::mem::swap(
-                    &mut state.render_frame.blit_front_bind_group,
-                    &mut state.render_frame.blit_back_bind_group,
-                );
-                std::mem::swap(
-                    &mut state.render_frame.sample_bind_group_front,
-                    &mut state.render_frame.sample_bind_group_back,
-                );
-                let mut encoder = self
-                    .device
-                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-                let mut splat_render_pass =
-                    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: None,
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &sample_view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(Color {
-                                    r: 0.0,
-                                    g: 0.0,
-                                    b: 0.0,
-                                    a: 1.0,
-                                }),
-                                store: wgpu::StoreOp::Store,
-                            },
-                        })],
-                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                            view: &depth_view,
-                            depth_ops: Some(wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(1.0),
-                                store: wgpu::StoreOp::Store,
-                            }),
-                            stencil_ops: None,
-                        }),
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                    });
-                scene.draw(
-                    &self.queue,
-                    &mut splat_render_pass,
-                    (state.render_resolution.width() * state.supersample) as i32,
-                    (state.render_resolution.height() * state.supersample) as i32,
-                    state.supersample,
-                    state.azimuth,
-                    state.elevation,
-                    state.zoom,
-                );
-
-                std::mem::drop(splat_render_pass);
-
-                let blit_view = state.render_frame.blit_front_texture.create_view(
-                    &wgpu::TextureViewDescriptor {
-                        format: Some(FRAME_FORMAT_FLOAT),
-                        ..Default::default()
-                    },
-                );
-
-                let mut downsample_render_pass =
-                    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: None,
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &blit_view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Load,
-                                store: wgpu::StoreOp::Store,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                    });
-                let supersample_vec = Vec4u::new(state.supersample, stale_camera as u32, 0, 0);
-                let supersample_vec: mint::Vector4<u32> = supersample_vec.into();
-                self.queue.write_buffer(
-                    &self.uniform_buffer,
-                    0,
-                    supersample_vec.as_std140().as_bytes(),
-                );
-                downsample_render_pass.set_pipeline(&self.downsample_pipeline);
-                downsample_render_pass.set_bind_group(
-                    0,
-                    &state.render_frame.sample_bind_group_front,
-                    &[],
-                );
-                downsample_render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
-                downsample_render_pass.draw(0..4, 0..1);
-
-                std::mem::drop(downsample_render_pass);
-
-                self.queue.submit(Some(encoder.finish()));
-            }
+            let settings = AccumulateSettings {
+                camera: state.camera(),
+                background: state.background,
+                mask_mode: state.mask_mode,
+                fixed_seed: state.fixed_seed,
+                target_samples: state.target_samples,
+                denoise: state.denoise,
+                denoise_strength: state.denoise_strength,
+                selection: state.selection_mode,
+                alpha_scale: state.alpha_scale,
+                sigma_scale: state.sigma_scale,
+                show_grid: state.show_grid,
+            };
+            self.accumulate(
+                scene,
+                &mut state.render_frame,
+                &settings,
+                &mut state.accumulated_samples,
+                subframe_count,
+                stale_camera,
+            );
         }
 
         let mut encoder = self
@@ -694,9 +1384,9 @@ impl Display {
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.01,
-                        g: 0.01,
-                        b: 0.01,
+                        r: state.letterbox_color[0] as f64,
+                        g: state.letterbox_color[1] as f64,
+                        b: state.letterbox_color[2] as f64,
                         a: 1.0,
                     }),
                     store: wgpu::StoreOp::Store,
@@ -708,25 +1398,66 @@ impl Display {
         });
 
         if state.scene.is_some() {
-            let frame_width = state.render_resolution.width();
-            let frame_height = state.render_resolution.height();
-
-            let frame_aspect = frame_width as f32 / frame_height as f32;
-            let canvas_aspect = canvas_width as f32 / canvas_height as f32;
-
-            if frame_aspect > canvas_aspect {
-                let box_width = canvas_width as f32;
-                let box_height = canvas_width as f32 / frame_aspect;
-                let border = (canvas_height as f32 - box_height) / 2.0;
-                render_pass.set_viewport(0.0, border, box_width, box_height, 0.0, 1.0);
+            let [x, y, box_width, box_height] = frame_viewport(
+                state.render_frame.resolution.aspect(),
+                canvas_width,
+                canvas_height,
+            );
+            render_pass.set_viewport(x, y, box_width, box_height, 0.0, 1.0);
+            // The mask shows raw coverage, untouched by any display transform.
+            let blit_globals = if state.mask_mode {
+                BlitGlobals {
+                    exposure: 1.0,
+                    srgb_encode: 0,
+                    upscale_filter: state.upscale_filter as u32,
+                    dither_seed: 0,
+                    tonemap: Tonemap::None as u32,
+                }
+            } else {
+                BlitGlobals {
+                    exposure: state.exposure.exp2(),
+                    srgb_encode: state.srgb_encode as u32,
+                    upscale_filter: state.upscale_filter as u32,
+                    // Changes every frame so the noise averages out instead of showing as a
+                    // pattern; zero disables it.
+                    dither_seed: if state.dither {
+                        state.accumulated_samples.wrapping_add(1).max(1)
+                    } else {
+                        0
+                    },
+                    tonemap: state.tonemap as u32,
+                }
+            };
+            self.queue.write_buffer(
+                &self.blit_uniform_buffer,
+                0,
+                blit_globals.as_std140().as_bytes(),
+            );
+            if state.view_mode == ViewMode::Depth {
+                render_pass.set_pipeline(&self.depth_view_pipeline);
+                render_pass.set_bind_group(0, &state.render_frame.depth_view_bind_group, &[]);
             } else {
-                let box_width = canvas_height as f32 * frame_aspect;
-                let box_height = canvas_height as f32;
-                let border = (canvas_width as f32 - box_width) / 2.0;
-                render_pass.set_viewport(border, 0.0, box_width, box_height, 0.0, 1.0);
+                render_pass.set_pipeline(&self.blit_pipeline);
+                render_pass.set_bind_group(0, &state.render_frame.blit_front_bind_group, &[]);
+                render_pass.set_bind_group(1, &self.blit_uniform_bind_group, &[]);
             }
-            render_pass.set_pipeline(&self.blit_pipeline);
-            render_pass.set_bind_group(0, &state.render_frame.blit_front_bind_group, &[]);
+            render_pass.draw(0..4, 0..1);
+        } else {
+            let aspect = state.render_frame.resolution.aspect();
+            let [x, y, box_width, box_height] = frame_viewport(aspect, canvas_width, canvas_height);
+            render_pass.set_viewport(x, y, box_width, box_height, 0.0, 1.0);
+            let placeholder_globals = PlaceholderGlobals {
+                background: state.background.into(),
+                time: self.created_at.elapsed().as_secs_f32(),
+                aspect,
+            };
+            self.queue.write_buffer(
+                &self.placeholder_uniform_buffer,
+                0,
+                placeholder_globals.as_std140().as_bytes(),
+            );
+            render_pass.set_pipeline(&self.placeholder_pipeline);
+            render_pass.set_bind_group(0, &self.placeholder_bind_group, &[]);
             render_pass.draw(0..4, 0..1);
         }
 
@@ -739,5 +1470,70 @@ impl Display {
         self.queue.submit(Some(encoder.finish()));
 
         surface_texture.present();
+
+        let first_scene_frame = state.scene.is_some() && !state.first_frame_signaled;
+        state.first_frame_signaled |= first_scene_frame;
+        first_scene_frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn sample_index_restarts_after_camera_change() {
+        let mut accumulated_samples = 37;
+        let mut indices = Vec::new();
+        for stale_camera in [true, false, false] {
+            let index = sample_index(accumulated_samples, stale_camera);
+            accumulated_samples = index + 1;
+            indices.push(index);
+        }
+        assert_eq!(indices, [0, 1, 2]);
+        assert_eq!(accumulated_samples, 3);
+    }
+
+    #[wasm_bindgen_test]
+    fn resolution_dimensions() {
+        let presets = [
+            (RenderResolution::Ws360P, (640, 360)),
+            (RenderResolution::Ws720P, (1280, 720)),
+            (RenderResolution::Ws1080P, (1920, 1080)),
+            (RenderResolution::Ws1440P, (2560, 1440)),
+            (RenderResolution::Ws2160P, (3840, 2160)),
+            (RenderResolution::Native(1000, 500), (1000, 500)),
+            (RenderResolution::Auto(300, 400), (300, 400)),
+        ];
+        for (resolution, dimensions) in presets {
+            assert_eq!(resolution.dimensions(), dimensions);
+            assert_eq!(resolution.width(), dimensions.0);
+            assert_eq!(resolution.height(), dimensions.1);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn resolution_aspect() {
+        assert!((RenderResolution::Ws1080P.aspect() - 16.0 / 9.0).abs() < 1e-6);
+        assert_eq!(RenderResolution::Native(1000, 500).aspect(), 2.0);
+        assert_eq!(RenderResolution::Auto(300, 400).aspect(), 0.75);
+    }
+
+    #[wasm_bindgen_test]
+    fn native_resolution_round_trips() {
+        let resolution = RenderResolution::Native(1234, 567);
+        let json = serde_json::to_string(&resolution).unwrap();
+        let parsed: RenderResolution = serde_json::from_str(&json).unwrap();
+        assert!(parsed == resolution);
+        assert_eq!(parsed.dimensions(), (1234, 567));
+    }
+
+    #[wasm_bindgen_test]
+    fn lower_steps_down_the_presets() {
+        assert!(RenderResolution::Ws2160P.lower() == Some(RenderResolution::Ws1440P));
+        assert!(RenderResolution::Native(1920, 1200).lower() == Some(RenderResolution::Ws1080P));
+        assert!(RenderResolution::Ws360P.lower().is_none());
     }
 }
@@ -0,0 +1,190 @@
+use wasm_bindgen::{Clamped, JsCast};
+
+use crate::display::{FramePixels, Tonemap};
+
+pub fn mask_rgba8(pixels: &FramePixels) -> Vec<u8> {
+    pixels
+        .rgba
+        .iter()
+        .flat_map(|texel| {
+            let coverage = (texel[0].clamp(0.0, 1.0) * 255.0).round() as u8;
+            [coverage, coverage, coverage, 255]
+        })
+        .collect()
+}
+
+fn linear_to_srgb(linear: f32) -> f32 {
+    let c = linear.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn tonemap(c: f32, operator: Tonemap) -> f32 {
+    match operator {
+        Tonemap::None => c,
+        Tonemap::Reinhard => c / (1.0 + c),
+        Tonemap::Aces => ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0),
+    }
+}
+
+// Matches blit.wgsl apart from dithering, so the file looks like the canvas.
+pub fn display_rgba8(
+    pixels: &FramePixels,
+    exposure: f32,
+    operator: Tonemap,
+    srgb_encode: bool,
+) -> Vec<u8> {
+    let scale = exposure.exp2();
+    pixels
+        .rgba
+        .iter()
+        .flat_map(|texel| {
+            let [r, g, b] = [texel[0], texel[1], texel[2]].map(|c| {
+                let c = tonemap(c * scale, operator);
+                let c = if srgb_encode { linear_to_srgb(c) } else { c };
+                (c.clamp(0.0, 1.0) * 255.0).round() as u8
+            });
+            [r, g, b, 255]
+        })
+        .collect()
+}
+
+const HASH_BLOCK: u32 = 4;
+
+// 8-bit averages over 4×4 blocks, so the hash ignores float noise below display precision.
+pub fn frame_hash(pixels: &FramePixels) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for block_y in 0..pixels.height / HASH_BLOCK {
+        for block_x in 0..pixels.width / HASH_BLOCK {
+            let mut sum = [0.0f32; 3];
+            for y in block_y * HASH_BLOCK..(block_y + 1) * HASH_BLOCK {
+                for x in block_x * HASH_BLOCK..(block_x + 1) * HASH_BLOCK {
+                    let texel = pixels.rgba[(y * pixels.width + x) as usize];
+                    for c in 0..3 {
+                        sum[c] += texel[c];
+                    }
+                }
+            }
+            for c in sum {
+                let level = (c / (HASH_BLOCK * HASH_BLOCK) as f32).clamp(0.0, 1.0) * 255.0;
+                hash ^= level.round() as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+    hash
+}
+
+// An OffscreenCanvas needs no document, so this also works where nothing is on screen.
+pub async fn encode_png(width: u32, height: u32, rgba8: &[u8]) -> Result<Vec<u8>, String> {
+    let canvas = web_sys::OffscreenCanvas::new(width, height)
+        .map_err(|_| "could not create export canvas".to_string())?;
+    let context: web_sys::OffscreenCanvasRenderingContext2d = canvas
+        .get_context("2d")
+        .ok()
+        .flatten()
+        .ok_or("could not get 2d context for export".to_string())?
+        .unchecked_into();
+    let image_data =
+        web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(rgba8), width, height)
+            .map_err(|_| "could not create image data".to_string())?;
+    context
+        .put_image_data(&image_data, 0.0, 0.0)
+        .map_err(|_| "could not draw image data".to_string())?;
+    let options = web_sys::ImageEncodeOptions::new();
+    options.set_type("image/png");
+    let blob = canvas
+        .convert_to_blob_with_options(&options)
+        .map_err(|_| "could not encode png".to_string())?;
+    let blob: web_sys::Blob = wasm_bindgen_futures::JsFuture::from(blob)
+        .await
+        .map_err(|_| "could not encode png".to_string())?
+        .unchecked_into();
+    let buffer = wasm_bindgen_futures::JsFuture::from(blob.array_buffer())
+        .await
+        .map_err(|_| "could not read encoded png".to_string())?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+pub fn download_png(file_name: &str, width: u32, height: u32, rgba8: &[u8]) -> Result<(), String> {
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let canvas: web_sys::HtmlCanvasElement = document
+        .create_element("canvas")
+        .map_err(|_| "could not create export canvas".to_string())?
+        .unchecked_into();
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let context: web_sys::CanvasRenderingContext2d = canvas
+        .get_context("2d")
+        .ok()
+        .flatten()
+        .ok_or("could not get 2d context for export".to_string())?
+        .unchecked_into();
+    let image_data =
+        web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(rgba8), width, height)
+            .map_err(|_| "could not create image data".to_string())?;
+    context
+        .put_image_data(&image_data, 0.0, 0.0)
+        .map_err(|_| "could not draw image data".to_string())?;
+    let url = canvas
+        .to_data_url_with_type("image/png")
+        .map_err(|_| "could not encode png".to_string())?;
+
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(|_| "could not create download link".to_string())?
+        .unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn gradient(width: u32, height: u32, offset: f32) -> FramePixels {
+        let rgba = (0..width * height)
+            .map(|i| {
+                let c = (i % width) as f32 / width as f32 + offset;
+                [c, c * 0.5, 1.0 - c, 1.0]
+            })
+            .collect();
+        FramePixels {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn frame_hash_is_stable() {
+        assert_eq!(
+            frame_hash(&gradient(16, 8, 0.0)),
+            frame_hash(&gradient(16, 8, 0.0))
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn frame_hash_ignores_noise_below_display_precision() {
+        assert_eq!(
+            frame_hash(&gradient(16, 8, 0.0)),
+            frame_hash(&gradient(16, 8, 0.0001))
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn frame_hash_changes_with_the_image() {
+        assert_ne!(
+            frame_hash(&gradient(16, 8, 0.0)),
+            frame_hash(&gradient(16, 8, 0.1))
+        );
+    }
+}
@@ -1,9 +1,114 @@
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
 use egui::Tooltip;
+use js_sys::Reflect;
+use wasm_bindgen::JsValue;
 use wgpu::Backend;
 
-use crate::{App, AppLogic, AppState, display::RenderResolution, pbar::Progress};
+use crate::{
+    App, AppLogic, AppState, EXPOSURE_STEP, MAX_EXPOSURE, MAX_FOV, MIN_EXPOSURE, MIN_FOV,
+    PinchMode, Preset, TrackpadMode,
+    display::{
+        MAX_SUPERSAMPLE, RenderResolution, Tonemap, UpscaleFilter, ViewMode, adapter_label,
+        frame_viewport, samples_per_pixel,
+    },
+    pbar::Progress,
+    scene::{SelectionMode, UpAxis},
+    utils::Vec3f,
+};
+
+const SUSTAINED_SLOW_SECS: f64 = 3.0;
+const FPS_CAPS: [u32; 4] = [24, 30, 60, 120];
+const FRAME_TIME_WINDOW: usize = 300;
+const FRAME_GRAPH_LEN: usize = 120;
+const TRANSIENT_MESSAGE_SECS: f32 = 1.5;
+const SCENE_NAME_CHARS: usize = 28;
+
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.0} MiB", bytes as f64 / (1u64 << 20) as f64)
+}
+
+// Keeps both ends, since URLs share prefixes and file names differ near the end.
+fn ellipsize(text: &str, max_chars: usize) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    if chars.len() <= max_chars {
+        return text.to_string();
+    }
+    let tail = max_chars / 2;
+    let head = max_chars - tail - 1;
+    let head = chars[..head].iter().collect::<String>();
+    let tail = chars[chars.len() - tail..].iter().collect::<String>();
+    format!("{}…{}", head, tail)
+}
+
+fn format_duration(secs: f64) -> String {
+    let secs = secs.ceil() as u64;
+    if secs < 60 {
+        format!("{} s", secs)
+    } else {
+        format!("{} min {} s", secs / 60, secs % 60)
+    }
+}
+
+#[derive(Default)]
+pub struct ThemeOverride {
+    pub dark: Option<bool>,
+    pub accent: Option<egui::Color32>,
+    pub font_size: Option<f32>,
+    pub font_family: Option<egui::FontFamily>,
+}
+
+impl ThemeOverride {
+    pub fn from_js(value: &JsValue) -> Result<Self, String> {
+        let get = |key: &str| {
+            Reflect::get(value, &key.into())
+                .ok()
+                .filter(|v| !v.is_undefined())
+        };
+        let accent = match get("accent").and_then(|v| v.as_string()) {
+            Some(hex) => Some(
+                egui::Color32::from_hex(&hex)
+                    .map_err(|_| format!("invalid accent color {}", hex))?,
+            ),
+            None => None,
+        };
+        let font_family = match get("font_family").and_then(|v| v.as_string()).as_deref() {
+            Some("monospace") => Some(egui::FontFamily::Monospace),
+            Some("proportional") => Some(egui::FontFamily::Proportional),
+            Some(other) => return Err(format!("unknown font family {}", other)),
+            None => None,
+        };
+        Ok(Self {
+            dark: get("dark").and_then(|v| v.as_bool()),
+            accent,
+            font_size: get("font_size")
+                .and_then(|v| v.as_f64())
+                .map(|size| size as f32),
+            font_family,
+        })
+    }
+
+    fn apply(&self, style: &mut egui::Style) {
+        style.override_font_id = Some(egui::FontId {
+            size: self.font_size.unwrap_or(14.0),
+            family: self
+                .font_family
+                .clone()
+                .unwrap_or(egui::FontFamily::Monospace),
+        });
+        style.visuals = if self.dark.unwrap_or(true) {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+        if let Some(accent) = self.accent {
+            style.visuals.selection.bg_fill = accent;
+            style.visuals.hyperlink_color = accent;
+            style.visuals.widgets.hovered.bg_stroke.color = accent;
+            style.visuals.widgets.active.bg_fill = accent;
+        }
+    }
+}
 
 pub struct GuiRenderData {
     pub textures_delta: egui::TexturesDelta,
@@ -12,45 +117,60 @@ pub struct GuiRenderData {
 }
 
 pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
-    let backend = app.display.backend;
+    let backend = app.display().backend;
+    let adapter_name = adapter_label(&app.display().adapter.get_info());
+    let display = app.display();
     let mut borrow = app.state.borrow_mut();
     let state: &mut AppState = &mut borrow;
 
     let raw_input = state.gui_state.take_egui_input(&app.window);
     let now = raw_input.time.unwrap_or(0.0);
     let dt = if let Some(last_frame_time) = state.last_frame_time {
-        (now - last_frame_time) / state.subframe_count as f64
+        (now - last_frame_time - state.throttle_time) / state.subframe_count as f64
     } else {
         0.015
     };
     state.last_frame_time = Some(now);
     state.avg_frame_time = 0.9 * state.avg_frame_time + 0.1 * dt;
+    if state.frame_times.len() == FRAME_TIME_WINDOW {
+        state.frame_times.pop_front();
+    }
+    state.frame_times.push_back(dt);
 
     let real_frame_time = state.avg_frame_time * state.subframe_count as f64;
-    if real_frame_time < 0.018 && state.scene.is_some() {
+    if real_frame_time < state.min_frame_time && state.scene.is_some() {
         state.subframe_count += 1;
-    } else if real_frame_time > 0.025 && state.subframe_count > 1 {
+    } else if real_frame_time > state.max_frame_time && state.subframe_count > 1 {
         state.subframe_count -= 1;
     }
 
+    if state.auto_quality
+        && state.scene.is_some()
+        && state.subframe_count == 1
+        && real_frame_time > state.max_frame_time
+    {
+        let slow_since = *state.slow_since.get_or_insert(now);
+        if now - slow_since > SUSTAINED_SLOW_SECS {
+            state.downgrade_quality();
+            state.slow_since = None;
+        }
+    } else {
+        state.slow_since = None;
+    }
+
     let real_size = app.window.inner_size();
     let width = real_size.width;
     let height = real_size.height;
 
+    let egui_ctx = state.gui_state.egui_ctx().clone();
     let egui::FullOutput {
         platform_output,
         textures_delta,
         shapes,
         pixels_per_point,
         viewport_output: _,
-    } = state.gui_state.egui_ctx().run(raw_input, |ctx| {
-        ctx.style_mut(|style| {
-            style.override_font_id = Some(egui::FontId {
-                size: 14.0f32,
-                family: egui::FontFamily::Monospace,
-            });
-            style.visuals = egui::Visuals::dark();
-        });
+    } = egui_ctx.run(raw_input, |ctx| {
+        ctx.style_mut(|style| state.theme.apply(style));
 
         egui::Window::new("panel")
             .default_width(0.0)
@@ -73,11 +193,28 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
                     {
                         state.chooser_open = !state.chooser_open;
                     }
+                    let reload_unavailable = state.reload_unavailable();
+                    if ui
+                        .add_enabled(
+                            reload_unavailable.is_none(),
+                            egui::widgets::Button::new(egui::RichText::new("🔄").size(28.0))
+                                .frame(false),
+                        )
+                        .on_hover_text("reload the current scene (r)")
+                        .on_disabled_hover_text(reload_unavailable.unwrap_or_default())
+                        .clicked()
+                    {
+                        let app = app.clone();
+                        wasm_bindgen_futures::spawn_local(async move {
+                            app.reload().await;
+                        });
+                    }
                     if ui
                         .add(
                             egui::widgets::Button::new(egui::RichText::new("➕").size(28.0))
                                 .frame(false),
                         )
+                        .on_hover_text("enlarge the interface (ctrl +)")
                         .clicked()
                     {
                         egui::gui_zoom::zoom_in(ctx);
@@ -87,10 +224,43 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
                             egui::widgets::Button::new(egui::RichText::new("➖").size(28.0))
                                 .frame(false),
                         )
+                        .on_hover_text("shrink the interface (ctrl -)")
                         .clicked()
                     {
                         egui::gui_zoom::zoom_out(ctx);
                     }
+                    if ui
+                        .add(
+                            egui::widgets::Button::new(egui::RichText::new("🔗").size(28.0))
+                                .frame(false),
+                        )
+                        .on_hover_text("copy a link to this view")
+                        .clicked()
+                    {
+                        copy_view_link(&state.pose_fragment());
+                        state.show_transient("copied link to this view".to_string());
+                    }
+                    if ui
+                        .add_enabled(
+                            state.scene.is_some(),
+                            egui::widgets::Button::new(egui::RichText::new("📷").size(28.0))
+                                .frame(false),
+                        )
+                        .on_hover_text(if state.converged() {
+                            "save a png screenshot".to_string()
+                        } else {
+                            format!(
+                                "save a png screenshot once accumulated ({}/{} samples)",
+                                state.accumulated_samples, state.target_samples
+                            )
+                        })
+                        .clicked()
+                    {
+                        let app = app.clone();
+                        wasm_bindgen_futures::spawn_local(async move {
+                            app.export_screenshot().await;
+                        });
+                    }
                     let playpause_symbol = if state.paused { "▶" } else { "⏸" };
                     if ui
                         .add(
@@ -103,6 +273,38 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
                     {
                         state.paused = !state.paused;
                     }
+                    if ui
+                        .add(
+                            egui::widgets::Button::new(egui::RichText::new("🔁").size(28.0))
+                                .frame(false)
+                                .selected(state.auto_rotate),
+                        )
+                        .on_hover_text("orbit the scene automatically")
+                        .clicked()
+                    {
+                        state.auto_rotate = !state.auto_rotate;
+                    }
+                    if ui
+                        .add(
+                            egui::widgets::Button::new(egui::RichText::new("🏠").size(28.0))
+                                .frame(false),
+                        )
+                        .on_hover_text("reset the camera to the default view")
+                        .clicked()
+                    {
+                        state.reset_camera();
+                        state.show_transient("camera: reset".to_string());
+                    }
+                    if ui
+                        .add(
+                            egui::widgets::Button::new(egui::RichText::new("🎯").size(28.0))
+                                .frame(false),
+                        )
+                        .on_hover_text("frame the whole scene")
+                        .clicked()
+                    {
+                        state.frame_scene();
+                    }
                     if ui
                         .add(
                             egui::widgets::Button::new(egui::RichText::new("⛶").size(28.0))
@@ -156,63 +358,578 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
                     ui.end_row();
 
 
+                    ui.label("gpu:");
+                    if state.adapters.len() > 1 {
+                        let mut adapter_request = state.adapter_request;
+                        let selected = state
+                            .adapters
+                            .iter()
+                            .find(|(request, _)| *request == adapter_request)
+                            .map_or_else(|| adapter_name.clone(), |(_, info)| adapter_label(info));
+                        egui::ComboBox::new("gpu", "")
+                            .selected_text(selected)
+                            .width(20.0)
+                            .show_ui(ui, |ui| {
+                                for (request, info) in &state.adapters {
+                                    ui.selectable_value(&mut adapter_request, *request, adapter_label(info));
+                                }
+                            });
+                        if adapter_request != state.adapter_request && !state.loading {
+                            let app = app.clone();
+                            wasm_bindgen_futures::spawn_local(async move {
+                                if let Err(err_string) = app.switch_adapter(adapter_request).await {
+                                    app.show_err(err_string);
+                                }
+                            });
+                        }
+                    } else {
+                        ui.label(&adapter_name).on_hover_text("the browser offers no other adapter");
+                    }
+                    ui.end_row();
+
+                    let quality_before = (state.render_resolution.clone(), state.supersample);
+
                     ui.label("resolution:");
                     egui::ComboBox::new("resolution", "")
                         .selected_text(state.render_resolution.to_string())
                         .width(20.0)
                         .show_ui(ui, |ui| {
-                            ui.selectable_value(
-                                &mut state.render_resolution,
-                                RenderResolution::Ws360P,
-                                RenderResolution::Ws360P.to_string(),
+                            let too_large = format!(
+                                "exceeds this GPU's maximum texture size of {}",
+                                display.max_texture_dimension()
                             );
-                            ui.selectable_value(
-                                &mut state.render_resolution,
+                            for resolution in [
+                                RenderResolution::Ws360P,
                                 RenderResolution::Ws720P,
-                                RenderResolution::Ws720P.to_string(),
-                            );
-                            ui.selectable_value(
-                                &mut state.render_resolution,
                                 RenderResolution::Ws1080P,
-                                RenderResolution::Ws1080P.to_string(),
-                            );
+                                RenderResolution::Ws1440P,
+                                RenderResolution::Ws2160P,
+                            ] {
+                                let label = resolution.to_string();
+                                ui.add_enabled_ui(display.resolution_fits(&resolution), |ui| {
+                                    ui.selectable_value(
+                                        &mut state.render_resolution,
+                                        resolution,
+                                        label,
+                                    )
+                                })
+                                .inner
+                                .on_disabled_hover_text(&too_large);
+                            }
+                            let native = RenderResolution::Native(width, height);
+                            ui.add_enabled_ui(display.resolution_fits(&native), |ui| {
+                                ui.selectable_value(
+                                    &mut state.render_resolution,
+                                    native.clone(),
+                                    format!("native: {}", native),
+                                )
+                            })
+                            .inner
+                            .on_disabled_hover_text(&too_large);
                             ui.selectable_value(
                                 &mut state.render_resolution,
-                                RenderResolution::Ws1440P,
-                                RenderResolution::Ws1440P.to_string(),
+                                RenderResolution::Auto(width, height),
+                                "auto: follow canvas",
                             );
+                        });
+                    ui.end_row();
+
+                    let samples_text = if state.effective_supersample == state.supersample {
+                        samples_per_pixel(state.supersample).to_string()
+                    } else {
+                        format!(
+                            "{} (clamped to {})",
+                            samples_per_pixel(state.supersample),
+                            samples_per_pixel(state.effective_supersample)
+                        )
+                    };
+                    let res = ui.link("samples:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Samples averaged into each pixel. The frame is rendered at a multiple of the resolution, so memory grows with the sample count.");
+                                if state.effective_supersample != state.supersample {
+                                    ui.label("At this resolution that frame would exceed this GPU's maximum texture size or the viewer's memory budget, so fewer samples are used. Lower the resolution to use more.");
+                                }
+                            });
+                    }
+                    egui::ComboBox::new("samples", "")
+                        .selected_text(samples_text)
+                        .width(20.0)
+                        .show_ui(ui, |ui| {
+                            let max_supersample = display
+                                .clamp_supersample(&state.render_resolution, MAX_SUPERSAMPLE);
+                            for supersample in 1..=MAX_SUPERSAMPLE {
+                                ui.add_enabled_ui(supersample <= max_supersample, |ui| {
+                                    ui.selectable_value(
+                                        &mut state.supersample,
+                                        supersample,
+                                        samples_per_pixel(supersample).to_string(),
+                                    )
+                                })
+                                .inner
+                                .on_disabled_hover_text(
+                                    "too large at this resolution for this GPU's maximum texture size or the sample memory budget",
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    // Once the user picks a quality themselves, stop overriding it.
+                    if state.quality_downgraded
+                        && quality_before != (state.render_resolution.clone(), state.supersample)
+                    {
+                        state.auto_quality = false;
+                    }
+
+                    let res = ui.link("auto quality:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Lower samples, then resolution, when frames stay slow for a few seconds.");
+                            });
+                    }
+                    ui.checkbox(&mut state.auto_quality, "");
+                    ui.end_row();
+
+                    if let Some(name) = &state.scene_name {
+                        ui.label("scene:");
+                        ui.label(ellipsize(name, SCENE_NAME_CHARS)).on_hover_text(name);
+                        ui.end_row();
+                    }
+
+                    if let Some(scene) = &state.scene {
+                        ui.label("triangles:");
+                        ui.label(scene.num_tris().to_string());
+                        ui.end_row();
+
+                        let (min, max) = scene.bounds();
+                        let (size, center) = (max - min, scene.bounds_center());
+                        ui.label("extent:");
+                        ui.label(format!("{:.2} × {:.2} × {:.2}", size.x, size.y, size.z))
+                            .on_hover_text(format!("bounding box centered at ({:.2}, {:.2}, {:.2})", center.x, center.y, center.z));
+                        ui.end_row();
+
+                        let res = ui.link("scene memory:");
+                        if res.contains_pointer() {
+                            Tooltip::for_widget(&res)
+                                .popup
+                                .show(|ui| {
+                                    ui.label(format!("Vertices {}, alpha and sigma {}, spherical harmonics {}. The rest is selection, sorting and culling state.", format_bytes(scene.vertex_bytes), format_bytes(scene.alpha_sigma_bytes), format_bytes(scene.sh_bytes)));
+                                });
+                        }
+                        ui.label(format_bytes(scene.gpu_bytes));
+                        ui.end_row();
+                    }
+
+                    let scene_bytes = state.scene.as_ref().map_or(0, |scene| scene.gpu_bytes);
+                    let frame_bytes = state.render_frame.gpu_bytes;
+                    let res = ui.link("vram:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label(format!("Estimated GPU memory: {} for the scene, {} for the render targets. The render targets grow with resolution × samples.", format_bytes(scene_bytes), format_bytes(frame_bytes)));
+                            });
+                    }
+                    ui.label(format_bytes(scene_bytes + frame_bytes));
+                    ui.end_row();
+
+                    let mut fov_degrees = state.fov.to_degrees();
+                    ui.label("fov:");
+                    if ui
+                        .add(egui::Slider::new(&mut fov_degrees, MIN_FOV.to_degrees()..=MAX_FOV.to_degrees()).suffix("°"))
+                        .changed()
+                    {
+                        state.set_fov(fov_degrees.to_radians());
+                    }
+                    ui.end_row();
+
+                    if let Some(up_axis) = state.scene.as_ref().map(|scene| scene.up_axis()) {
+                        let res = ui.link("up axis:");
+                        if res.contains_pointer() {
+                            Tooltip::for_widget(&res)
+                                .popup
+                                .show(|ui| {
+                                    ui.label("File uses the up direction stored in the scene, or the garden capture's tuning for files without one. The others override it for scenes that render sideways.");
+                                });
+                        }
+                        let mut selected = up_axis;
+                        egui::ComboBox::from_id_salt("up_axis")
+                            .selected_text(selected.to_string())
+                            .show_ui(ui, |ui| {
+                                for axis in [UpAxis::File, UpAxis::PlusY, UpAxis::MinusY, UpAxis::PlusZ, UpAxis::MinusZ] {
+                                    ui.selectable_value(&mut selected, axis, axis.to_string());
+                                }
+                            });
+                        ui.end_row();
+                        if selected != up_axis {
+                            state.set_up_axis(selected);
+                        }
+                    }
+
+                    if let Some((pitch, roll)) = state.scene.as_ref().map(|scene| scene.up_tilt()) {
+                        let mut pitch_degrees = pitch.to_degrees();
+                        let mut roll_degrees = roll.to_degrees();
+
+                        ui.label("level pitch:");
+                        let pitch_changed = ui
+                            .add(egui::Slider::new(&mut pitch_degrees, -45.0..=45.0).suffix("°"))
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("level roll:");
+                        let roll_changed = ui
+                            .add(egui::Slider::new(&mut roll_degrees, -45.0..=45.0).suffix("°"))
+                            .changed();
+                        ui.end_row();
+
+                        if pitch_changed || roll_changed {
+                            state.set_up_tilt(pitch_degrees.to_radians(), roll_degrees.to_radians());
+                        }
+                    }
+
+                    if let Some((num_tris, (mut start, mut end))) =
+                        state.scene.as_ref().map(|scene| (scene.num_tris(), scene.draw_range()))
+                    {
+                        ui.label("first triangle:");
+                        let start_changed = ui.add(egui::Slider::new(&mut start, 0..=num_tris)).changed();
+                        ui.end_row();
+
+                        ui.label("end triangle:");
+                        let end_changed = ui.add(egui::Slider::new(&mut end, 0..=num_tris)).changed();
+                        ui.end_row();
+
+                        if let Some(num_culled) = state.scene.as_ref().and_then(|scene| scene.num_culled()) {
+                            ui.label("culled:");
+                            ui.label(format!("{} of {}", num_culled, end - start));
+                            ui.end_row();
+                        }
+
+                        if start_changed {
+                            state.set_draw_range(start, end.max(start));
+                        } else if end_changed {
+                            state.set_draw_range(start.min(end), end);
+                        }
+                    }
+
+                    let res = ui.link("depth bias:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Offsets splat depth to reduce flicker between coplanar triangles. A few units of constant bias and a slope of 0–2 are usually enough; large values let hidden triangles show through.");
+                            });
+                    }
+                    ui.horizontal(|ui| {
+                        let constant_changed = ui
+                            .add(egui::DragValue::new(&mut state.depth_bias.constant).range(-16..=16))
+                            .changed();
+                        let slope_changed = ui
+                            .add(egui::DragValue::new(&mut state.depth_bias.slope_scale).range(-4.0..=4.0).speed(0.05).prefix("slope "))
+                            .changed();
+                        if constant_changed || slope_changed {
+                            state.stale_camera = true;
+                        }
+                    });
+                    ui.end_row();
+
+                    if state.auto_rotate {
+                        ui.label("turntable:");
+                        let mut speed_degrees = state.auto_rotate_speed.to_degrees();
+                        if ui
+                            .add(egui::DragValue::new(&mut speed_degrees).range(-90.0..=90.0).speed(0.5).suffix("°/s"))
+                            .changed()
+                        {
+                            state.auto_rotate_speed = speed_degrees.to_radians();
+                        }
+                        ui.end_row();
+                    }
+
+                    ui.label("lock elevation:");
+                    ui.checkbox(&mut state.lock_elevation, "");
+                    ui.end_row();
+
+                    let res = ui.link("stay outside:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Keep the camera from zooming inside the scene's fit radius.");
+                            });
+                    }
+                    ui.checkbox(&mut state.stay_outside, "");
+                    ui.end_row();
+
+                    ui.label("trackpad:");
+                    egui::ComboBox::new("trackpad", "")
+                        .selected_text(state.trackpad_mode.to_string())
+                        .width(20.0)
+                        .show_ui(ui, |ui| {
                             ui.selectable_value(
-                                &mut state.render_resolution,
-                                RenderResolution::Ws2160P,
-                                RenderResolution::Ws2160P.to_string(),
+                                &mut state.trackpad_mode,
+                                TrackpadMode::Zoom,
+                                TrackpadMode::Zoom.to_string(),
                             );
                             ui.selectable_value(
-                                &mut state.render_resolution,
-                                RenderResolution::Native(width, height),
-                                format!("native: {}", RenderResolution::Native(width, height)),
+                                &mut state.trackpad_mode,
+                                TrackpadMode::PanPinchZoom,
+                                TrackpadMode::PanPinchZoom.to_string(),
                             );
                         });
                     ui.end_row();
 
-                    ui.label("samples:");
-                    egui::ComboBox::new("samples", "")
-                        .selected_text((state.supersample * state.supersample).to_string())
+                    ui.label("upscale:");
+                    egui::ComboBox::new("upscale", "")
+                        .selected_text(state.upscale_filter.to_string())
                         .width(20.0)
                         .show_ui(ui, |ui| {
-                            ui.selectable_value(&mut state.supersample, 1, "1".to_owned());
-                            ui.selectable_value(&mut state.supersample, 2, "4".to_owned());
-                            ui.selectable_value(&mut state.supersample, 3, "9".to_owned());
-                            ui.selectable_value(&mut state.supersample, 4, "16".to_owned());
-                            ui.selectable_value(&mut state.supersample, 5, "25".to_owned());
+                            for filter in [
+                                UpscaleFilter::Nearest,
+                                UpscaleFilter::Bilinear,
+                                UpscaleFilter::Sharpen,
+                            ] {
+                                ui.selectable_value(
+                                    &mut state.upscale_filter,
+                                    filter,
+                                    filter.to_string(),
+                                );
+                            }
                         });
                     ui.end_row();
 
+                    ui.label("pinch:");
+                    egui::ComboBox::new("pinch", "")
+                        .selected_text(state.pinch_mode.to_string())
+                        .width(20.0)
+                        .show_ui(ui, |ui| {
+                            for mode in [PinchMode::Dolly, PinchMode::Fov] {
+                                ui.selectable_value(&mut state.pinch_mode, mode, mode.to_string());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("background:");
+                    if ui.color_edit_button_rgb(&mut state.background).changed() {
+                        state.stale_camera = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("letterbox:");
+                    ui.color_edit_button_rgb(&mut state.letterbox_color);
+                    ui.end_row();
+
+                    if let Some(scene) = &mut state.scene {
+                        let res = ui.link("gamma:");
+                        if res.contains_pointer() {
+                            Tooltip::for_widget(&res)
+                                .popup
+                                .show(|ui| {
+                                    ui.label("Scenes without full spherical harmonics only carry the DC color term, which looks flatter. Raise gamma to match other viewers. Resets when a scene is loaded.");
+                                });
+                        }
+                        if ui.add(egui::Slider::new(&mut scene.gamma, 0.5..=2.5)).changed() {
+                            state.stale_camera = true;
+                        }
+                        ui.end_row();
+                    }
+
+                    let res = ui.link("opacity:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Multiplies every triangle's alpha, fading the whole scene to see inside dense regions.");
+                            });
+                    }
+                    if ui.add(egui::Slider::new(&mut state.alpha_scale, 0.0..=1.0)).changed() {
+                        state.stale_camera = true;
+                    }
+                    ui.end_row();
+
+                    let res = ui.link("sharpness:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Multiplies every triangle's sigma. Above 1 the falloff tightens and triangles shrink towards their centers, below 1 they spread to their edges. Useful for judging whether a model is over- or under-smoothed.");
+                            });
+                    }
+                    if ui.add(egui::Slider::new(&mut state.sigma_scale, 0.1..=10.0).logarithmic(true)).changed() {
+                        state.stale_camera = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("guides:");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut state.crosshair, "crosshair");
+                        ui.checkbox(&mut state.thirds_guides, "thirds");
+                        ui.checkbox(&mut state.axis_gizmo, "axes");
+                    });
+                    ui.end_row();
+
+                    let res = ui.link("grid:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Draw a ground grid under the scene, perpendicular to its up axis. Major lines are ten minor cells apart.");
+                            });
+                    }
+                    if ui.checkbox(&mut state.show_grid, "").changed() {
+                        state.stale_camera = true;
+                    }
+                    ui.end_row();
+
+                    let res = ui.link("preset:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Figure uses a white background and letterbox for print, viewer restores the dark defaults. Both reset exposure and turn off the mask view.");
+                            });
+                    }
+                    ui.horizontal(|ui| {
+                        for preset in [Preset::Viewer, Preset::Figure] {
+                            if ui.button(preset.to_string()).clicked() {
+                                state.apply_preset(preset);
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    let res = ui.link("fixed seed:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Restart the noise sequence whenever the view changes and stop after a fixed number of samples, so the converged image is reproducible.");
+                            });
+                    }
+                    if ui.checkbox(&mut state.fixed_seed, "").changed() {
+                        state.stale_camera = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("dither:");
+                    ui.checkbox(&mut state.dither, "");
+                    ui.end_row();
+
+                    let res = ui.link("denoise:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Keep clamped history while the camera moves instead of restarting accumulation. Lower strength rejects more history, reducing ghosting at the cost of noise.");
+                            });
+                    }
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut state.denoise, "");
+                        if state.denoise {
+                            ui.add(
+                                egui::Slider::new(&mut state.denoise_strength, 0.25..=4.0)
+                                    .text("strength"),
+                            );
+                        }
+                    });
+                    ui.end_row();
+
+                    if state.scene.is_some() {
+                        ui.label("mask:");
+                        ui.horizontal(|ui| {
+                            if ui.checkbox(&mut state.mask_mode, "").changed() {
+                                state.stale_camera = true;
+                            }
+                            if state.mask_mode && ui.button("export png").clicked() {
+                                let app = app.clone();
+                                wasm_bindgen_futures::spawn_local(async move {
+                                    app.export_mask().await;
+                                });
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    let num_selected = state.scene.as_ref().map_or(0, |scene| scene.num_selected());
+                    if num_selected > 0 {
+                        ui.label("selection:");
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} triangles", num_selected));
+                            egui::ComboBox::from_id_salt("selection_mode")
+                                .selected_text(state.selection_mode.to_string())
+                                .show_ui(ui, |ui| {
+                                    for mode in [SelectionMode::Highlight, SelectionMode::Hide] {
+                                        if ui.selectable_value(&mut state.selection_mode, mode, mode.to_string()).changed() {
+                                            state.stale_camera = true;
+                                        }
+                                    }
+                                });
+                            if ui.button("clear").clicked()
+                                && let Some(scene) = &mut state.scene
+                            {
+                                scene.clear_selection(&app.display().queue);
+                                state.stale_camera = true;
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    let res = ui.link("skip degenerate:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Drop zero-area triangles when the next scene is loaded.");
+                            });
+                    }
+                    ui.checkbox(&mut state.filter_degenerate, "");
+                    ui.end_row();
+
+                    ui.label("exposure:");
+                    let mut exposure = state.exposure;
+                    if ui
+                        .add(egui::Slider::new(&mut exposure, MIN_EXPOSURE..=MAX_EXPOSURE).step_by(EXPOSURE_STEP as f64).suffix(" stops"))
+                        .changed()
+                    {
+                        state.set_exposure(exposure);
+                    }
+                    ui.end_row();
+
+                    let res = ui.link("srgb encode:");
+                    if res.contains_pointer() {
+                        Tooltip::for_widget(&res)
+                            .popup
+                            .show(|ui| {
+                                ui.label("Treat rendered colors as linear and encode them to sRGB for display. Leave off for scenes trained on sRGB images, which is what the training renderer shows. Screenshots follow this setting.");
+                            });
+                    }
+                    ui.checkbox(&mut state.srgb_encode, "");
+                    ui.end_row();
+
                     let frame_rate = 1.0 / state.avg_frame_time;
 
                     ui.label("frame rate:");
                     ui.label(format!("{:.0}/s", frame_rate));
                     ui.end_row();
 
+                    if state.scene.is_some() {
+                        ui.label("samples/pixel:");
+                        ui.horizontal(|ui| {
+                            if state.converged() {
+                                ui.label("converged");
+                            } else {
+                                ui.label(format!(
+                                    "accumulating {}/{}",
+                                    state.accumulated_samples, state.target_samples
+                                ));
+                            }
+                            ui.add(egui::DragValue::new(&mut state.target_samples).range(1..=65536).prefix("target "));
+                        });
+                        ui.end_row();
+                    }
+
                     let res = ui.link("subframes:");
                     if res.contains_pointer() {
                         Tooltip::for_widget(&res)
@@ -225,11 +942,153 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
                     ui.end_row();
                 });
 
+                if state.playlist.len() > 1 {
+                    egui::CollapsingHeader::new("playlist").default_open(true).show(ui, |ui| {
+                        let count = state.playlist.len();
+                        let current = state.playlist_index;
+                        let mut play_index = None;
+                        ui.horizontal(|ui| {
+                            if ui.button("◀").on_hover_text("previous scene").clicked() {
+                                play_index = Some((current + count - 1) % count);
+                            }
+                            if ui.button("▶").on_hover_text("next scene").clicked() {
+                                play_index = Some((current + 1) % count);
+                            }
+                            ui.label(format!("{} of {}", current + 1, count));
+                        });
+                        for (index, file) in state.playlist.iter().enumerate() {
+                            if ui.selectable_label(index == current, file.name()).clicked() {
+                                play_index = Some(index);
+                            }
+                        }
+                        if let Some(index) = play_index {
+                            let app = app.clone();
+                            wasm_bindgen_futures::spawn_local(async move {
+                                app.play(index);
+                            });
+                        }
+                    });
+                }
+
+                egui::CollapsingHeader::new("frame pacing").show(ui, |ui| {
+                    egui::Grid::new("frame_pacing_grid").show(ui, |ui| {
+                        ui.label("start subframes:");
+                        ui.add(egui::DragValue::new(&mut state.initial_subframe_count).range(1..=64));
+                        ui.end_row();
+
+                        let mut min_frame_ms = state.min_frame_time * 1000.0;
+                        let mut max_frame_ms = state.max_frame_time * 1000.0;
+
+                        ui.label("grow below:");
+                        let min_changed = ui
+                            .add(egui::DragValue::new(&mut min_frame_ms).range(1.0..=100.0).speed(0.1).suffix(" ms"))
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("shrink above:");
+                        let max_changed = ui
+                            .add(egui::DragValue::new(&mut max_frame_ms).range(1.0..=100.0).speed(0.1).suffix(" ms"))
+                            .changed();
+                        ui.end_row();
+
+                        if min_changed || max_changed {
+                            state.set_frame_time_thresholds(min_frame_ms / 1000.0, max_frame_ms / 1000.0);
+                        }
+
+                        if let Some((low, stddev)) = frame_time_spread(&state.frame_times) {
+                            let res = ui.link("1% low:");
+                            if res.contains_pointer() {
+                                Tooltip::for_widget(&res)
+                                    .popup
+                                    .show(|ui| {
+                                        ui.label("Mean of the slowest 1% of recent frame times, which shows stutter the average hides.");
+                                    });
+                            }
+                            ui.label(format!("{:.1} ms ({:.0}/s)", low * 1000.0, 1.0 / low));
+                            ui.end_row();
+
+                            ui.label("jitter:");
+                            ui.label(format!("±{:.2} ms", stddev * 1000.0));
+                            ui.end_row();
+                        }
+
+                        ui.label("history:");
+                        frame_time_graph(ui, &state.frame_times);
+                        ui.end_row();
+
+                        ui.label("fps cap:");
+                        egui::ComboBox::from_id_salt("fps_cap")
+                            .selected_text(state.fps_cap.map_or("off".to_string(), |cap| format!("{}/s", cap)))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut state.fps_cap, None, "off");
+                                for cap in FPS_CAPS {
+                                    ui.selectable_value(&mut state.fps_cap, Some(cap), format!("{}/s", cap));
+                                }
+                            });
+                        ui.end_row();
+                    });
+                });
+
+                egui::CollapsingHeader::new("display").show(ui, |ui| {
+                    egui::Grid::new("display_grid").show(ui, |ui| {
+                        let res = ui.link("tonemap:");
+                        if res.contains_pointer() {
+                            Tooltip::for_widget(&res)
+                                .popup
+                                .show(|ui| {
+                                    ui.label("Compresses highlights after exposure instead of clipping them. Only the displayed image changes, so accumulation carries on.");
+                                });
+                        }
+                        egui::ComboBox::from_id_salt("tonemap")
+                            .selected_text(state.tonemap.to_string())
+                            .show_ui(ui, |ui| {
+                                for operator in [Tonemap::None, Tonemap::Reinhard, Tonemap::Aces] {
+                                    ui.selectable_value(&mut state.tonemap, operator, operator.to_string());
+                                }
+                            });
+                        ui.end_row();
+
+                        let res = ui.link("view mode:");
+                        if res.contains_pointer() {
+                            Tooltip::for_widget(&res)
+                                .popup
+                                .show(|ui| {
+                                    ui.label("Depth shows the splat depth buffer of the latest sample, white near and black at the far plane on a log scale.");
+                                });
+                        }
+                        egui::ComboBox::from_id_salt("view_mode")
+                            .selected_text(state.view_mode.to_string())
+                            .show_ui(ui, |ui| {
+                                for mode in [ViewMode::Color, ViewMode::Depth] {
+                                    ui.selectable_value(&mut state.view_mode, mode, mode.to_string());
+                                }
+                            });
+                        ui.end_row();
+                    });
+                });
+
                 ui.separator();
                 ui.vertical_centered(|ui| {
                     ui.label("controls:");
-                    ui.label("click+drag to rotate");
-                    ui.label("scroll to zoom");
+                    if state.lock_elevation {
+                        ui.label("click+drag to turn");
+                    } else {
+                        ui.label("click+drag to rotate");
+                    }
+                    ui.label("middle-drag to pan");
+                    if state.trackpad_mode == TrackpadMode::PanPinchZoom {
+                        ui.label("scroll to pan, pinch to zoom");
+                    } else {
+                        ui.label("scroll to zoom, +shift fine, +alt coarse");
+                    }
+                    ui.label("wasd to fly, q e down/up, +shift faster");
+                    ui.label("[ ] to adjust exposure");
+                    ui.label(", . to adjust fov");
+                    ui.label("f to fit scene, 1 to reset distance");
+                    ui.label("t to toggle last two resolutions");
+                    ui.label("r to reload the scene");
+                    ui.label("shift+drag to select, +alt to add");
+                    ui.label("ctrl + ctrl - to scale the interface");
                 });
                 ui.separator();
             });
@@ -251,11 +1110,56 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
                             egui::ComboBox::from_label("scene")
                                 .selected_text("")
                                 .show_ui(ui, |ui| {
-                                    for &(url, name) in &state.known_scenes {
-                                        ui.selectable_value(&mut selected_url, url, name);
+                                    let mut groups: Vec<Option<&str>> = Vec::new();
+                                    for known_scene in &state.known_scenes {
+                                        if !groups.contains(&known_scene.group) {
+                                            groups.push(known_scene.group);
+                                        }
+                                    }
+                                    groups.sort_by_key(|group| group.is_some());
+                                    for group in groups {
+                                        if let Some(group) = group {
+                                            ui.label(egui::RichText::new(group).strong());
+                                        }
+                                        for known_scene in &state.known_scenes {
+                                            if known_scene.group == group {
+                                                ui.selectable_value(
+                                                    &mut selected_url,
+                                                    known_scene.url,
+                                                    known_scene.name,
+                                                );
+                                            }
+                                        }
                                     }
                                 });
                         });
+                        ui.label("\nor\n");
+                        if ui.button("open local file…").clicked() {
+                            let app = app.clone();
+                            wasm_bindgen_futures::spawn_local(async move {
+                                app.open_file_picker().await;
+                            });
+                        }
+                        ui.label("\nor\n");
+                        let mut load_typed_url = false;
+                        ui.horizontal(|ui| {
+                            let res = ui.add(
+                                egui::TextEdit::singleline(&mut state.url_input)
+                                    .hint_text("https://…/scene.tsplat"),
+                            );
+                            load_typed_url = res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                            load_typed_url |= ui.button("load").clicked();
+                        });
+                        let typed_url = state.url_input.trim().to_string();
+                        if load_typed_url && !typed_url.is_empty() {
+                            let app = app.clone();
+                            wasm_bindgen_futures::spawn_local(async move {
+                                app.state.borrow_mut().begin_loading();
+                                if let Err(err_string) = app.load_url(typed_url).await {
+                                    app.show_err(err_string);
+                                }
+                            });
+                        }
                         ui.label("\nNote that this scene is displayed at a reduced fidelity to fit within github size limits.\n");
 
                         if selected_url != "" {
@@ -285,6 +1189,9 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
                             egui::ProgressBar::new(state.progress_bar.get_progress()).animate(true),
                         );
                         ui.label(format!("\n{}…\n", state.progress_bar.get_status()));
+                        if let Some((rate, eta)) = state.progress_bar.get_rate_eta() {
+                            ui.label(format!("{:.1} MB/s, {} left", rate / 1e6, format_duration(eta)));
+                        }
                     });
                 });
         }
@@ -309,6 +1216,89 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
         if !error_open {
             state.error_message = None;
         }
+
+        if state.scene.is_some() && (state.crosshair || state.thirds_guides || state.axis_gizmo) {
+            let [x, y, box_width, box_height] =
+                frame_viewport(state.render_frame.resolution.aspect(), width, height);
+            let frame = egui::Rect::from_min_size(
+                egui::pos2(x, y) / ctx.pixels_per_point(),
+                egui::vec2(box_width, box_height) / ctx.pixels_per_point(),
+            );
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            let stroke = egui::Stroke::new(1.0, egui::Color32::from_white_alpha(160));
+            if state.thirds_guides {
+                for t in [1.0 / 3.0, 2.0 / 3.0] {
+                    let x = frame.lerp_inside(egui::vec2(t, 0.0)).x;
+                    let y = frame.lerp_inside(egui::vec2(0.0, t)).y;
+                    painter.vline(x, frame.y_range(), stroke);
+                    painter.hline(frame.x_range(), y, stroke);
+                }
+            }
+            if state.crosshair {
+                let center = frame.center();
+                let arm = 12.0;
+                painter.hline(center.x - arm..=center.x + arm, center.y, stroke);
+                painter.vline(center.x, center.y - arm..=center.y + arm, stroke);
+            }
+            if state.axis_gizmo
+                && let Some(scene) = &state.scene
+            {
+                axis_gizmo(&painter, frame, scene.view_basis(&state.camera()));
+            }
+        }
+
+        if let Some(start) = state.box_select_start {
+            let to_points = |(x, y): (f32, f32)| egui::pos2(x, y) / ctx.pixels_per_point();
+            let rect = egui::Rect::from_two_pos(to_points(start), to_points(state.prev_mouse_pos));
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("box_select"),
+            ));
+            let accent = ctx.style().visuals.selection.bg_fill;
+            painter.rect(
+                rect,
+                0.0,
+                accent.gamma_multiply(0.2),
+                egui::Stroke::new(1.0, accent),
+                egui::StrokeKind::Inside,
+            );
+        }
+
+        let mut warning_open = state.warning_message.is_some();
+        if warning_open {
+            egui::Window::new("warning")
+                .title_bar(true)
+                .open(&mut warning_open)
+                .collapsible(false)
+                .resizable([false, false])
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.label(format!(
+                            "\n{}\n",
+                            state.warning_message.as_ref().unwrap_or(&"".to_string())
+                        ));
+                    });
+                });
+        }
+        if !warning_open {
+            state.warning_message = None;
+        }
+
+        if let Some((message, shown_at)) = &state.transient_message {
+            if shown_at.elapsed().as_secs_f32() < TRANSIENT_MESSAGE_SECS {
+                egui::Area::new(egui::Id::new("transient_message"))
+                    .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -40.0])
+                    .interactable(false)
+                    .show(ctx, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.label(message);
+                        });
+                    });
+            } else {
+                state.transient_message = None;
+            }
+        }
     });
 
     (
@@ -320,3 +1310,102 @@ pub fn show_gui(app: &Arc<App>) -> (egui::PlatformOutput, GuiRenderData) {
         },
     )
 }
+
+// Returns the 1%-low frame time and the frame time standard deviation.
+fn frame_time_spread(frame_times: &VecDeque<f64>) -> Option<(f64, f64)> {
+    if frame_times.len() < 2 {
+        return None;
+    }
+    let mut sorted = frame_times.iter().copied().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    let slowest = &sorted[..sorted.len().div_ceil(100)];
+    let low = slowest.iter().sum::<f64>() / slowest.len() as f64;
+
+    let mean = sorted.iter().sum::<f64>() / sorted.len() as f64;
+    let variance = sorted.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / sorted.len() as f64;
+    Some((low, variance.sqrt()))
+}
+
+const GIZMO_ARM: f32 = 28.0;
+const GIZMO_MARGIN: f32 = 16.0;
+
+// World axes under the view rotation alone, in the frame's bottom-right corner.
+fn axis_gizmo(
+    painter: &egui::Painter,
+    frame: egui::Rect,
+    (right, up, forward): (Vec3f, Vec3f, Vec3f),
+) {
+    let origin =
+        frame.right_bottom() - egui::vec2(GIZMO_MARGIN + GIZMO_ARM, GIZMO_MARGIN + GIZMO_ARM);
+    let mut axes = [
+        ("x", Vec3f::x(), egui::Color32::from_rgb(230, 80, 80)),
+        ("y", Vec3f::y(), egui::Color32::from_rgb(100, 200, 90)),
+        ("z", Vec3f::z(), egui::Color32::from_rgb(90, 140, 240)),
+    ];
+    // Farthest first, so the axis pointing at the viewer is painted on top.
+    axes.sort_by(|a, b| b.1.dot(&forward).total_cmp(&a.1.dot(&forward)));
+    for (name, axis, color) in axes {
+        let arm = egui::vec2(axis.dot(&right), -axis.dot(&up)) * GIZMO_ARM;
+        painter.line_segment([origin, origin + arm], egui::Stroke::new(2.0, color));
+        // An axis along the view direction has no length on screen to push its label along.
+        let label_offset = if arm.length() > 1.0 {
+            arm.normalized() * 8.0
+        } else {
+            egui::Vec2::ZERO
+        };
+        painter.text(
+            origin + arm + label_offset,
+            egui::Align2::CENTER_CENTER,
+            name,
+            egui::FontId::proportional(12.0),
+            color,
+        );
+    }
+}
+
+// Sparkline of the most recent frame times, scaled to the slowest one shown.
+fn frame_time_graph(ui: &mut egui::Ui, frame_times: &VecDeque<f64>) {
+    let recent = frame_times
+        .iter()
+        .skip(frame_times.len().saturating_sub(FRAME_GRAPH_LEN))
+        .copied()
+        .collect::<Vec<_>>();
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(160.0, 40.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    if recent.len() < 2 {
+        return;
+    }
+
+    let min = recent.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = recent.iter().copied().fold(0.0, f64::max);
+    let avg = recent.iter().sum::<f64>() / recent.len() as f64;
+    let points = recent
+        .iter()
+        .enumerate()
+        .map(|(i, &dt)| {
+            let x = rect.left() + rect.width() * i as f32 / (FRAME_GRAPH_LEN - 1) as f32;
+            let y = rect.bottom() - rect.height() * (dt / max.max(1e-6)) as f32;
+            egui::pos2(x, y)
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.0, ui.visuals().selection.bg_fill),
+    ));
+    response.on_hover_text(format!(
+        "min {:.1} ms, max {:.1} ms, avg {:.1} ms",
+        min * 1000.0,
+        max * 1000.0,
+        avg * 1000.0
+    ));
+}
+
+fn copy_view_link(fragment: &str) {
+    let window = web_sys::window().unwrap();
+    let location = window.location();
+    let _ = location.set_hash(fragment);
+    if let Ok(href) = location.href() {
+        let _ = window.navigator().clipboard().write_text(&href);
+    }
+}
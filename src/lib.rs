@@ -1,46 +1,245 @@
+mod bench;
+mod compute;
 mod display;
+mod export;
 mod gui;
 mod load;
 mod pbar;
+mod picker;
 mod scene;
+mod settings;
 mod utils;
+mod worker;
 
-use std::{cell::RefCell, sync::Arc};
+pub use display::RenderResolution;
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    f32::consts::PI,
+    fmt::Formatter,
+    rc::Rc,
+    sync::Arc,
+};
 
 use futures::AsyncRead;
 use pbar::Progress;
+use utils::Vec3f;
 use wasm_bindgen::prelude::*;
 use web_sys::HtmlCanvasElement;
+use web_time::Instant;
 use winit::{
     application::ApplicationHandler,
+    event::TouchPhase,
     event_loop::EventLoop,
+    keyboard::{KeyCode, PhysicalKey},
     platform::web::{EventLoopExtWebSys, WindowAttributesExtWebSys, WindowExtWebSys},
     window::Window,
 };
 
+thread_local! {
+    static APP: RefCell<Option<Arc<App>>> = const { RefCell::new(None) };
+    // Shared by every thumbnail, since each device holds a WebGL context and browsers cap those.
+    static OFFSCREEN_DISPLAY: RefCell<Option<Rc<display::Display>>> = const { RefCell::new(None) };
+}
+
+const FETCH_FAILED: &str =
+    "could not fetch scene data; check the URL and that its server allows cross-origin requests";
+const RELOAD_UNAVAILABLE: &str =
+    "can't reload a dropped file, open it with the file picker to enable reload";
+
+const MIN_FOV: f32 = 20.0 * PI / 180.0;
+const MAX_FOV: f32 = 120.0 * PI / 180.0;
+const FOV_STEP: f32 = 1.0 * PI / 180.0;
+
+const DEFAULT_BACKGROUND: [f32; 3] = [0.0, 0.0, 0.0];
+const FIGURE_BACKGROUND: [f32; 3] = [1.0, 1.0, 1.0];
+const DEFAULT_LETTERBOX_COLOR: [f32; 3] = [0.01, 0.01, 0.01];
+const FIGURE_LETTERBOX_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+
+const MIN_EXPOSURE: f32 = -4.0;
+const MAX_EXPOSURE: f32 = 4.0;
+const EXPOSURE_STEP: f32 = 0.1;
+
+const DEFAULT_AZIMUTH: f32 = -0.72;
+const DEFAULT_ELEVATION: f32 = 0.32;
+const DEFAULT_ZOOM: f32 = 3.0;
+const DEFAULT_FOV: f32 = 0.85;
+// Just short of the poles, where the orbit direction becomes parallel to up.
+const MAX_ELEVATION: f32 = PI / 2.0 - 0.01;
+const FINE_ZOOM_SCALE: f32 = 0.2;
+const COARSE_ZOOM_SCALE: f32 = 5.0;
+// Orbit distances per second.
+const FLY_SPEED: f32 = 0.5;
+const FLY_BOOST: f32 = 4.0;
+const FLY_KEYS: [KeyCode; 6] = [
+    KeyCode::KeyW,
+    KeyCode::KeyA,
+    KeyCode::KeyS,
+    KeyCode::KeyD,
+    KeyCode::KeyQ,
+    KeyCode::KeyE,
+];
+const PAN_SPEED: f32 = 0.002;
+
+const MIN_FRAME_TIME_GAP: f64 = 0.001;
+const FRAME_REBUILD_DELAY_SECS: f32 = 0.25;
+const PLACEHOLDER_FPS: u32 = 30;
+const DEFAULT_AUTO_ROTATE_SPEED: f32 = 10.0 * PI / 180.0;
+// How far the view may turn from the last sort direction before triangles are re-sorted.
+const RESORT_ANGLE: f32 = 15.0 * PI / 180.0;
+const THUMBNAIL_SIZE: u32 = 512;
+const THUMBNAIL_SAMPLES: u32 = 64;
+const MAX_THUMBNAIL_SAMPLES: u32 = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TrackpadMode {
+    Zoom,
+    PanPinchZoom,
+}
+
+impl std::fmt::Display for TrackpadMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackpadMode::Zoom => write!(f, "scroll zooms"),
+            TrackpadMode::PanPinchZoom => write!(f, "scroll pans"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PinchMode {
+    Dolly,
+    Fov,
+}
+
+impl std::fmt::Display for PinchMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PinchMode::Dolly => write!(f, "dolly"),
+            PinchMode::Fov => write!(f, "fov"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Preset {
+    Viewer,
+    Figure,
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Preset::Viewer => write!(f, "viewer"),
+            Preset::Figure => write!(f, "figure"),
+        }
+    }
+}
+
+enum SceneSource {
+    Url(String),
+    File { handle: Option<JsValue> },
+}
+
+struct KnownScene {
+    group: Option<&'static str>,
+    url: &'static str,
+    name: &'static str,
+}
+
 struct AppState {
     scene: Option<scene::Scene>,
     render_resolution: display::RenderResolution,
+    prev_render_resolution: Option<display::RenderResolution>,
+    // Per-axis scale of the sample targets; see display::samples_per_pixel.
     supersample: u32,
+    effective_supersample: u32,
     render_frame: display::RenderFrame,
+    pending_frame: Option<(display::RenderResolution, u32, Instant)>,
+    adapter_request: display::AdapterRequest,
+    // Empty on WebGL, where switching would only get the same context back.
+    adapters: Vec<(display::AdapterRequest, wgpu::AdapterInfo)>,
     gui_state: egui_winit::State,
     last_frame_time: Option<f64>,
     subframe_count: u32,
+    initial_subframe_count: u32,
+    min_frame_time: f64,
+    max_frame_time: f64,
     avg_frame_time: f64,
+    frame_times: VecDeque<f64>,
+    fps_cap: Option<u32>,
+    auto_quality: bool,
+    quality_downgraded: bool,
+    slow_since: Option<f64>,
+    throttle_time: f64,
     panel_open: bool,
     chooser_open: bool,
-    known_scenes: Vec<(&'static str, &'static str)>,
+    url_input: String,
+    known_scenes: Vec<KnownScene>,
     file_hovered: bool,
     loading: bool,
+    resort_pending: bool,
+    scene_source: Option<SceneSource>,
+    scene_name: Option<String>,
+    // A scene the current GPU could not hold after a switch, kept so switching back restores it.
+    stranded_scene: Option<load::TSplat>,
+    playlist: Vec<web_sys::File>,
+    playlist_index: usize,
+    load_generation: u64,
+    fetch_abort: Option<web_sys::AbortController>,
     paused: bool,
     progress_bar: pbar::ProgressBar,
     error_message: Option<String>,
+    warning_message: Option<String>,
     azimuth: f32,
     elevation: f32,
     prev_mouse_pos: (f32, f32),
     mouse_dragging: bool,
+    auto_rotate: bool,
+    auto_rotate_speed: f32,
+    mouse_panning: bool,
+    box_select_start: Option<(f32, f32)>,
+    selection_mode: scene::SelectionMode,
     zoom: f32,
+    fov: f32,
+    pan_offset: Vec3f,
+    translation: Vec3f,
+    fly_keys: HashSet<KeyCode>,
+    touches: HashMap<u64, (f32, f32)>,
+    lock_elevation: bool,
+    crosshair: bool,
+    show_grid: bool,
+    axis_gizmo: bool,
+    thirds_guides: bool,
+    stay_outside: bool,
+    trackpad_mode: TrackpadMode,
+    pinch_mode: PinchMode,
+    modifiers: winit::keyboard::ModifiersState,
+    exposure: f32,
+    srgb_encode: bool,
+    tonemap: display::Tonemap,
+    view_mode: display::ViewMode,
+    upscale_filter: display::UpscaleFilter,
+    dither: bool,
+    fixed_seed: bool,
+    accumulated_samples: u32,
+    target_samples: u32,
+    mask_mode: bool,
+    alpha_scale: f32,
+    sigma_scale: f32,
+    depth_bias: scene::DepthBias,
+    denoise: bool,
+    denoise_strength: f32,
+    filter_degenerate: bool,
+    atlas_filter: wgpu::FilterMode,
+    letterbox_color: [f32; 3],
+    background: [f32; 3],
+    saved_settings: settings::Settings,
+    theme: gui::ThemeOverride,
     stale_camera: bool,
+    first_frame_signaled: bool,
+    transient_message: Option<(String, Instant)>,
 }
 
 impl AppState {
@@ -48,98 +247,729 @@ impl AppState {
         self.file_hovered = false;
         self.chooser_open = false;
         self.loading = true;
+        self.cancel_load();
+        self.stranded_scene = None;
+        self.warning_message = None;
         self.progress_bar.update_progress_sync(0.0);
+        self.progress_bar.finish_bytes();
         self.progress_bar
             .update_status_sync("fetching scene data".to_string());
     }
+
+    fn reset_camera(&mut self) {
+        self.azimuth = DEFAULT_AZIMUTH;
+        self.elevation = DEFAULT_ELEVATION;
+        self.zoom = DEFAULT_ZOOM;
+        self.fov = DEFAULT_FOV;
+        self.pan_offset = Vec3f::zeros();
+        self.translation = Vec3f::zeros();
+        self.stale_camera = true;
+    }
+
+    // Supersedes any load in flight: its results are dropped and its download is aborted.
+    fn cancel_load(&mut self) {
+        self.load_generation += 1;
+        if let Some(controller) = self.fetch_abort.take() {
+            controller.abort();
+        }
+    }
+
+    fn unload(&mut self) {
+        self.cancel_load();
+        self.loading = false;
+        self.scene = None;
+        self.stranded_scene = None;
+        self.scene_name = None;
+        self.first_frame_signaled = false;
+        self.reset_camera();
+    }
+
+    // Writes the persisted settings to localStorage whenever one of them changed.
+    fn persist_settings(&mut self) {
+        let settings = settings::Settings {
+            render_resolution: self.render_resolution.clone(),
+            supersample: self.supersample,
+            panel_open: self.panel_open,
+            background: self.background,
+            ..self.saved_settings.clone()
+        };
+        if settings != self.saved_settings {
+            settings::save(&settings);
+            self.saved_settings = settings;
+        }
+    }
+
+    fn reset_accumulation(&mut self) {
+        self.stale_camera = true;
+    }
+
+    fn converged(&self) -> bool {
+        self.accumulated_samples >= self.target_samples
+    }
+
+    fn camera(&self) -> scene::Camera {
+        scene::Camera {
+            azimuth: self.azimuth,
+            elevation: self.elevation,
+            zoom: self.zoom,
+            fov: self.fov,
+            pan_offset: self.pan_offset,
+            translation: self.translation,
+        }
+    }
+
+    fn pan_camera(&mut self, delta_x: f32, delta_y: f32) {
+        if let Some(scene) = &self.scene {
+            let (right, up, _) = scene.view_basis(&self.camera());
+            let scale = PAN_SPEED * self.zoom;
+            self.pan_offset += scale * (-delta_x * right + delta_y * up);
+            self.stale_camera = true;
+        }
+    }
+
+    fn resort_direction(&mut self) -> Option<Vec3f> {
+        let scene = self.scene.as_ref().filter(|scene| !scene.has_gpu_sort())?;
+        let (_, _, forward) = scene.view_basis(&self.camera());
+        if self.resort_pending || forward.angle(&scene.sort_direction()) < RESORT_ANGLE {
+            return None;
+        }
+        self.resort_pending = true;
+        Some(forward)
+    }
+
+    fn orbit(&mut self, delta_x: f32, delta_y: f32) {
+        let delta_y = if self.lock_elevation { 0.0 } else { delta_y };
+        self.azimuth -= delta_x * 0.01;
+        // Clamping the stored value means reversing at a pole responds at once.
+        self.elevation = (self.elevation + delta_y * 0.01).clamp(-MAX_ELEVATION, MAX_ELEVATION);
+        if delta_x.abs() > 0.1 || delta_y.abs() > 0.1 {
+            self.stale_camera = true;
+        }
+    }
+
+    fn touch(&mut self, touch: winit::event::Touch) {
+        let position = (touch.location.x as f32, touch.location.y as f32);
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(touch.id, position);
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+            }
+            TouchPhase::Moved => {
+                // Comparing the same set of fingers before and after this move keeps a
+                // finger lifting mid-pinch from registering as motion.
+                let (centroid_before, spread_before) = touch_centroid_spread(&self.touches);
+                let Some(previous) = self.touches.insert(touch.id, position) else {
+                    return;
+                };
+                if self.touches.len() == 1 {
+                    self.orbit(position.0 - previous.0, position.1 - previous.1);
+                } else {
+                    let (centroid_after, spread_after) = touch_centroid_spread(&self.touches);
+                    if spread_before > 0.0 && spread_after > 0.0 {
+                        self.zoom *= spread_before / spread_after;
+                        self.stale_camera = true;
+                    }
+                    self.pan_camera(
+                        centroid_after.0 - centroid_before.0,
+                        centroid_after.1 - centroid_before.1,
+                    );
+                }
+            }
+        }
+    }
+
+    // Turntable orbit for demos, held while the user drags the view.
+    fn spin_turntable(&mut self, dt: f32) {
+        if self.auto_rotate && !self.mouse_dragging && self.scene.is_some() {
+            self.azimuth += self.auto_rotate_speed * dt;
+            self.stale_camera = true;
+        }
+    }
+
+    fn fly(&mut self, dt: f32) {
+        if self.fly_keys.is_empty() {
+            return;
+        }
+        if let Some(scene) = &self.scene {
+            let (right, up, forward) = scene.view_basis(&self.camera());
+            let mut direction = Vec3f::zeros();
+            for key in &self.fly_keys {
+                direction += match key {
+                    KeyCode::KeyW => forward,
+                    KeyCode::KeyS => -forward,
+                    KeyCode::KeyD => right,
+                    KeyCode::KeyA => -right,
+                    KeyCode::KeyE => up,
+                    KeyCode::KeyQ => -up,
+                    _ => Vec3f::zeros(),
+                };
+            }
+            let boost = if self.modifiers.shift_key() {
+                FLY_BOOST
+            } else {
+                1.0
+            };
+            self.translation += FLY_SPEED * boost * self.zoom * dt * direction;
+            self.stale_camera = true;
+        }
+    }
+
+    fn orbit_center(&self) -> Option<Vec3f> {
+        self.scene
+            .as_ref()
+            .map(|scene| scene.view_center(&self.camera()))
+    }
+
+    fn set_orbit_center(&mut self, center: Vec3f) {
+        if let Some(scene) = &self.scene {
+            self.pan_offset = center - scene.center() - self.translation;
+            self.stale_camera = true;
+        }
+    }
+
+    // Compact camera pose for shareable links, e.g. az=-0.72&el=0.32&zoom=3.
+    fn pose_fragment(&self) -> String {
+        let mut fragment = format!(
+            "az={:.3}&el={:.3}&zoom={:.3}&fov={:.3}",
+            self.azimuth, self.elevation, self.zoom, self.fov
+        );
+        let offset = self.pan_offset + self.translation;
+        if offset != Vec3f::zeros() {
+            fragment += &format!("&pan={:.3},{:.3},{:.3}", offset.x, offset.y, offset.z);
+        }
+        fragment
+    }
+
+    // Missing or non-finite values keep the current ones.
+    fn set_orbit_pose(
+        &mut self,
+        azimuth: Option<f32>,
+        elevation: Option<f32>,
+        zoom: Option<f32>,
+        fov: Option<f32>,
+    ) {
+        if let Some(azimuth) = azimuth.filter(|azimuth| azimuth.is_finite()) {
+            self.azimuth = azimuth;
+        }
+        if let Some(elevation) = elevation.filter(|elevation| elevation.is_finite()) {
+            self.elevation = elevation.clamp(-MAX_ELEVATION, MAX_ELEVATION);
+        }
+        if let Some(zoom) = zoom.filter(|zoom| zoom.is_finite() && *zoom > 0.0) {
+            self.zoom = zoom;
+        }
+        if let Some(fov) = fov.filter(|fov| fov.is_finite()) {
+            self.set_fov(fov);
+        }
+        self.stale_camera = true;
+    }
+
+    fn apply_pose(&mut self, params: &web_sys::UrlSearchParams) {
+        let get = |name| params.get(name).and_then(|value| value.parse::<f32>().ok());
+        self.set_orbit_pose(get("az"), get("el"), get("zoom"), get("fov"));
+        let pan = params.get("pan").and_then(|pan| {
+            let coords = pan
+                .split(',')
+                .map(|c| c.parse::<f32>().ok().filter(|c| c.is_finite()))
+                .collect::<Option<Vec<_>>>()?;
+            (coords.len() == 3).then(|| Vec3f::new(coords[0], coords[1], coords[2]))
+        });
+        if let Some(pan) = pan {
+            self.pan_offset = pan;
+            self.translation = Vec3f::zeros();
+        }
+        self.stale_camera = true;
+    }
+
+    fn set_up_axis(&mut self, up_axis: scene::UpAxis) {
+        if let Some(scene) = &mut self.scene {
+            scene.set_up_axis(up_axis);
+            self.stale_camera = true;
+        }
+    }
+
+    fn set_up_tilt(&mut self, pitch: f32, roll: f32) {
+        if let Some(scene) = &mut self.scene {
+            scene.set_up_tilt(pitch, roll);
+            self.stale_camera = true;
+        }
+    }
+
+    fn select_box(
+        &mut self,
+        queue: &wgpu::Queue,
+        start: (f32, f32),
+        end: (f32, f32),
+        canvas_size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        let aspect = self.render_frame.resolution.aspect();
+        let [x, y, width, height] =
+            display::frame_viewport(aspect, canvas_size.width, canvas_size.height);
+        let to_ndc =
+            |(px, py): (f32, f32)| [(px - x) / width * 2.0 - 1.0, 1.0 - (py - y) / height * 2.0];
+        let (a, b) = (to_ndc(start), to_ndc(end));
+        let min = [a[0].min(b[0]), a[1].min(b[1])];
+        let max = [a[0].max(b[0]), a[1].max(b[1])];
+        let additive = self.modifiers.alt_key();
+        let camera = self.camera();
+        if let Some(scene) = &mut self.scene {
+            scene.select_rect(queue, &camera, aspect, min, max, additive);
+            self.stale_camera = true;
+        }
+    }
+
+    fn set_draw_range(&mut self, start: usize, end: usize) {
+        if let Some(scene) = &mut self.scene {
+            scene.set_draw_range(start, end);
+            self.stale_camera = true;
+        }
+    }
+
+    // Distance at which a sphere of this radius just fits the narrower field of view of the frame.
+    fn fit_distance(&self, radius: f32) -> f32 {
+        let aspect = self.render_frame.resolution.aspect();
+        let half_fov = (self.fov / 2.0).min((aspect * (self.fov / 2.0).tan()).atan());
+        radius / half_fov.sin()
+    }
+
+    fn zoom_to_fit(&mut self) {
+        if let Some(scene) = &self.scene {
+            self.zoom = self.fit_distance(scene.fit_radius());
+            self.stale_camera = true;
+            self.show_transient("camera: zoom to fit".to_string());
+        }
+    }
+
+    // Centers the orbit on the scene's bounding box, ignoring outlying floaters, and backs off
+    // until all of it is in view. The orbit center is the scene's default center moved by
+    // `pan_offset`, so that carries it.
+    fn frame_scene(&mut self) {
+        if let Some(scene) = &self.scene {
+            let (min, max) = scene.fit_bounds();
+            self.pan_offset = (min + max) / 2.0 - scene.center();
+            self.translation = Vec3f::zeros();
+            self.zoom = self.fit_distance((max - min).norm() / 2.0);
+            self.stale_camera = true;
+            self.show_transient("camera: frame scene".to_string());
+        }
+    }
+
+    fn clamp_zoom_outside(&mut self) {
+        if self.stay_outside
+            && let Some(scene) = &self.scene
+        {
+            let min_zoom = scene.min_outside_zoom(&self.camera());
+            if self.zoom < min_zoom {
+                self.zoom = min_zoom;
+                self.stale_camera = true;
+            }
+        }
+    }
+
+    fn reset_zoom(&mut self) {
+        self.zoom = DEFAULT_ZOOM;
+        self.stale_camera = true;
+        self.show_transient("camera: reset distance".to_string());
+    }
+
+    fn set_frame_time_thresholds(&mut self, min_frame_time: f64, max_frame_time: f64) {
+        self.min_frame_time = min_frame_time.max(MIN_FRAME_TIME_GAP);
+        self.max_frame_time = max_frame_time.max(self.min_frame_time + MIN_FRAME_TIME_GAP);
+    }
+
+    fn scroll_zoom(&mut self, steps: f32) {
+        let step_scale = if self.modifiers.shift_key() {
+            FINE_ZOOM_SCALE
+        } else if self.modifiers.alt_key() {
+            COARSE_ZOOM_SCALE
+        } else {
+            1.0
+        };
+        self.zoom *= 1.01f32.powf(steps * step_scale);
+        self.stale_camera = true;
+    }
+
+    fn pinch(&mut self, scale: f32) {
+        match self.pinch_mode {
+            PinchMode::Dolly => {
+                self.zoom *= scale;
+                self.stale_camera = true;
+            }
+            PinchMode::Fov => self.set_fov(self.fov * scale),
+        }
+    }
+
+    fn set_fov(&mut self, fov: f32) {
+        let fov = fov.clamp(MIN_FOV, MAX_FOV);
+        if fov != self.fov {
+            self.fov = fov;
+            self.stale_camera = true;
+        }
+    }
+
+    fn apply_preset(&mut self, preset: Preset) {
+        (self.background, self.letterbox_color) = match preset {
+            Preset::Viewer => (DEFAULT_BACKGROUND, DEFAULT_LETTERBOX_COLOR),
+            Preset::Figure => (FIGURE_BACKGROUND, FIGURE_LETTERBOX_COLOR),
+        };
+        self.exposure = 0.0;
+        self.mask_mode = false;
+        self.view_mode = display::ViewMode::Color;
+        self.crosshair = false;
+        self.thirds_guides = false;
+        self.show_grid = false;
+        self.axis_gizmo = false;
+        self.stale_camera = true;
+        self.show_transient(format!("preset: {}", preset));
+    }
+
+    fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure.clamp(MIN_EXPOSURE, MAX_EXPOSURE);
+    }
+
+    fn show_transient(&mut self, message: String) {
+        self.transient_message = Some((message, Instant::now()));
+    }
+
+    fn downgrade_quality(&mut self) {
+        if self.effective_supersample > 1 {
+            self.supersample = self.effective_supersample - 1;
+            self.show_transient(format!(
+                "running slowly, lowered samples to {}",
+                display::samples_per_pixel(self.supersample)
+            ));
+        } else if let Some(lower) = self.render_resolution.lower() {
+            self.show_transient(format!("running slowly, lowered resolution to {}", lower));
+            self.render_resolution = lower;
+        } else {
+            return;
+        }
+        self.quality_downgraded = true;
+    }
+
+    fn nudge_fov(&mut self, delta: f32) {
+        self.set_fov(self.fov + delta);
+        self.show_transient(format!("fov: {:.0}°", self.fov.to_degrees()));
+    }
+
+    fn reload_unavailable(&self) -> Option<&'static str> {
+        match self.scene_source {
+            None => Some("no scene to reload"),
+            Some(SceneSource::File { handle: None }) => Some(RELOAD_UNAVAILABLE),
+            _ if self.loading => Some("a scene is already loading"),
+            _ => None,
+        }
+    }
+
+    fn toggle_resolution(&mut self) {
+        if let Some(prev) = self.prev_render_resolution.clone() {
+            self.show_transient(format!("resolution: {}", prev));
+            self.render_resolution = prev;
+        }
+    }
+
+    fn nudge_exposure(&mut self, delta: f32) {
+        self.set_exposure(self.exposure + delta);
+        self.show_transient(format!("exposure: {:+.1}", self.exposure));
+    }
 }
 
 struct App {
     window: Arc<Window>,
-    display: display::Display,
+    display: RefCell<Rc<display::Display>>,
     state: RefCell<AppState>,
 }
 
 impl App {
-    async fn new(window: Window, canvas: &HtmlCanvasElement) -> Self {
+    async fn new(window: Window, canvas: &HtmlCanvasElement) -> Result<Self, String> {
         let window = Arc::new(window);
 
-        let display = display::Display::from_canvas(canvas).await;
+        let adapter_request = display::AdapterRequest::default();
+        let display = display::Display::from_canvas(canvas, adapter_request).await?;
+        let adapters = if display.backend == wgpu::Backend::BrowserWebGpu {
+            display::enumerate_adapters().await
+        } else {
+            Vec::new()
+        };
 
         let egui_ctx = egui::Context::default();
         let gui_state =
             egui_winit::State::new(egui_ctx, egui::ViewportId::ROOT, &window, None, None, None);
 
-        let known_scenes = vec![("/triangle_splatting/garden.tsplat", "garden")];
+        let known_scenes = vec![KnownScene {
+            group: None,
+            url: "/triangle_splatting/garden.tsplat",
+            name: "garden",
+        }];
 
-        let render_resolution = display::RenderResolution::Ws720P;
-        let supersample = 3;
-        let render_frame = display.create_render_frame(&render_resolution, supersample);
+        let saved_settings = settings::load();
+        // Settings saved on another GPU may ask for a frame this one can't allocate.
+        let render_resolution = display.fit_resolution(&saved_settings.render_resolution);
+        let supersample = saved_settings.supersample;
+        let effective_supersample = display.clamp_supersample(&render_resolution, supersample);
+        let render_frame = display.create_render_frame(&render_resolution, effective_supersample);
 
         let state = RefCell::new(AppState {
             scene: None,
             render_resolution,
+            prev_render_resolution: None,
             supersample,
+            effective_supersample,
             render_frame,
+            pending_frame: None,
+            adapter_request,
+            adapters,
             gui_state,
             last_frame_time: None,
             subframe_count: 1,
+            initial_subframe_count: 1,
+            min_frame_time: 0.018,
+            max_frame_time: 0.025,
             avg_frame_time: 1.0 / 60.0,
-            panel_open: true,
+            frame_times: VecDeque::new(),
+            fps_cap: None,
+            auto_quality: false,
+            quality_downgraded: false,
+            slow_since: None,
+            throttle_time: 0.0,
+            panel_open: saved_settings.panel_open,
             chooser_open: true,
+            url_input: String::new(),
             known_scenes,
             file_hovered: false,
             loading: false,
+            resort_pending: false,
+            scene_source: None,
+            load_generation: 0,
+            fetch_abort: None,
+            scene_name: None,
+            stranded_scene: None,
+            playlist: vec![],
+            playlist_index: 0,
             paused: false,
             progress_bar: pbar::make_progress_bar(),
             error_message: None,
-            azimuth: -0.72,
-            elevation: 0.32,
+            warning_message: None,
+            azimuth: DEFAULT_AZIMUTH,
+            elevation: DEFAULT_ELEVATION,
             prev_mouse_pos: (0.0, 0.0),
             mouse_dragging: false,
-            zoom: 3.0,
+            auto_rotate: false,
+            auto_rotate_speed: DEFAULT_AUTO_ROTATE_SPEED,
+            mouse_panning: false,
+            box_select_start: None,
+            selection_mode: scene::SelectionMode::Highlight,
+            zoom: DEFAULT_ZOOM,
+            fov: DEFAULT_FOV,
+            pan_offset: Vec3f::zeros(),
+            translation: Vec3f::zeros(),
+            fly_keys: HashSet::new(),
+            touches: HashMap::new(),
+            lock_elevation: false,
+            crosshair: false,
+            show_grid: false,
+            axis_gizmo: false,
+            thirds_guides: false,
+            stay_outside: false,
+            trackpad_mode: TrackpadMode::Zoom,
+            pinch_mode: PinchMode::Dolly,
+            modifiers: winit::keyboard::ModifiersState::empty(),
+            exposure: 0.0,
+            srgb_encode: false,
+            tonemap: display::Tonemap::None,
+            view_mode: display::ViewMode::Color,
+            upscale_filter: display::UpscaleFilter::Nearest,
+            dither: false,
+            fixed_seed: false,
+            accumulated_samples: 0,
+            target_samples: 256,
+            mask_mode: false,
+            alpha_scale: 1.0,
+            sigma_scale: 1.0,
+            depth_bias: scene::DepthBias::default(),
+            denoise: false,
+            denoise_strength: 1.5,
+            filter_degenerate: false,
+            atlas_filter: wgpu::FilterMode::Nearest,
+            letterbox_color: DEFAULT_LETTERBOX_COLOR,
+            background: saved_settings.background,
+            saved_settings,
+            theme: gui::ThemeOverride::default(),
             stale_camera: true,
+            first_frame_signaled: false,
+            transient_message: None,
         });
 
-        App {
+        Ok(App {
             window,
-            display,
+            display: RefCell::new(Rc::new(display)),
             state,
-        }
+        })
+    }
+}
+
+impl App {
+    fn display(&self) -> Rc<display::Display> {
+        self.display.borrow().clone()
     }
 }
 
 trait AppLogic {
-    async fn load_scene<S: AsyncRead + Unpin>(&self, byte_stream: S) -> Result<(), String>;
+    async fn load_scene<S: AsyncRead + Unpin>(
+        &self,
+        name: String,
+        byte_stream: S,
+    ) -> Result<(), String>;
 
     async fn load_url(&self, url: String) -> Result<(), String>;
 
+    async fn switch_adapter(&self, request: display::AdapterRequest) -> Result<(), String>;
+
+    fn load_file(&self, file: web_sys::File, handle: Option<JsValue>);
+
+    fn play(&self, index: usize);
+
+    async fn reload(&self);
+
+    async fn open_file_picker(&self);
+
+    async fn resort(&self, direction: Vec3f);
+
+    async fn export_mask(&self);
+
+    async fn wait_for_converged_frame(&self) -> Result<(), String>;
+
+    async fn export_screenshot(&self);
+
+    async fn frame_hash(&self) -> Result<u64, String>;
+
+    fn handle_command(&self, command: JsValue);
+
     fn show_err(&self, err_string: String);
 }
 
 impl AppLogic for Arc<App> {
-    async fn load_scene<S: AsyncRead + Unpin>(&self, byte_stream: S) -> Result<(), String> {
+    async fn load_scene<S: AsyncRead + Unpin>(
+        &self,
+        name: String,
+        byte_stream: S,
+    ) -> Result<(), String> {
         let app = self.clone();
         let pbar = app.state.borrow().progress_bar.clone();
+        let generation = {
+            let state = app.state.borrow();
+            if !state.loading {
+                return Ok(());
+            }
+            state.load_generation
+        };
+        let cancelled = || app.state.borrow().load_generation != generation;
 
-        let tsplat = load::read_tsplat(byte_stream, pbar.clone()).await?;
+        let preview_app = app.clone();
+        let filter_degenerate = app.state.borrow().filter_degenerate;
+        let atlas_filter = app.state.borrow().atlas_filter;
+        let tsplat = worker::read_tsplat(
+            byte_stream,
+            pbar.clone(),
+            filter_degenerate,
+            move |preview| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    let preview_scene = scene::Scene::new(
+                        preview,
+                        &preview_app.display(),
+                        atlas_filter,
+                        pbar::make_progress_bar(),
+                    )
+                    .await;
+                    let state = &mut preview_app.state.borrow_mut();
+                    if let Ok(preview_scene) = preview_scene
+                        && state.loading
+                        && state.load_generation == generation
+                    {
+                        state.scene = Some(preview_scene);
+                        state.stale_camera = true;
+                    }
+                });
+            },
+        )
+        .await;
+        // An unloaded or superseded load finishes silently instead of reporting errors.
+        let tsplat = match tsplat {
+            _ if cancelled() => return Ok(()),
+            result => result?,
+        };
 
-        let new_scene = scene::Scene::new(tsplat, &app.display, pbar).await?;
+        let incomplete = tsplat.incomplete;
+        let display = app.display();
+        let new_scene = scene::Scene::new(tsplat, &display, atlas_filter, pbar).await;
+        let new_scene = match new_scene {
+            _ if cancelled() => return Ok(()),
+            result => result?,
+        };
+        if !Rc::ptr_eq(&display, &app.display()) {
+            return Err("gpu changed while loading, please load the scene again".to_string());
+        }
 
-        let state = &mut app.state.borrow_mut();
-        state.scene = Some(new_scene);
-        state.loading = false;
-        state.stale_camera = true;
+        let detail = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&detail, &"name".into(), &name.as_str().into());
+        let _ = js_sys::Reflect::set(
+            &detail,
+            &"triangles".into(),
+            &(new_scene.num_tris() as f64).into(),
+        );
+        let _ = js_sys::Reflect::set(&detail, &"incomplete".into(), &incomplete.into());
+        let _ = js_sys::Reflect::set(
+            &detail,
+            &"gpu_bytes".into(),
+            &(new_scene.gpu_bytes as f64).into(),
+        );
+
+        {
+            let state = &mut app.state.borrow_mut();
+            state.scene = Some(new_scene);
+            state.scene_name = Some(name);
+            state.loading = false;
+            state.stale_camera = true;
+            state.subframe_count = state.initial_subframe_count;
+            if incomplete {
+                state.warning_message = Some(
+                    "scene may be incomplete: the download ended early, showing the triangles received"
+                        .to_string(),
+                );
+            }
+        }
+        // Listeners may call back into the viewer, so the state is released first.
+        if let Some(canvas) = app.window.canvas() {
+            utils::dispatch_canvas_event(&canvas, "tsplat-loaded", &detail);
+        }
 
         Ok(())
     }
 
     async fn load_url(&self, url: String) -> Result<(), String> {
         let app = self.clone();
-        let response =
-            wasm_bindgen_futures::JsFuture::from(web_sys::window().unwrap().fetch_with_str(&url))
-                .await
-                .map_err(|_| "could not fetch scene data".to_string())?;
+        {
+            let mut state = app.state.borrow_mut();
+            state.scene_source = Some(SceneSource::Url(url.clone()));
+            // A scene picked by hand leaves the dropped playlist.
+            state.playlist.clear();
+        }
+        let init = web_sys::RequestInit::new();
+        if let Ok(controller) = web_sys::AbortController::new() {
+            init.set_signal(Some(&controller.signal()));
+            app.state.borrow_mut().fetch_abort = Some(controller);
+        }
+        let generation = app.state.borrow().load_generation;
+        let fetch = web_sys::window()
+            .unwrap()
+            .fetch_with_str_and_init(&url, &init);
+        let response = match wasm_bindgen_futures::JsFuture::from(fetch).await {
+            Ok(response) => response,
+            // Aborted because a newer load superseded this one.
+            Err(_) if app.state.borrow().load_generation != generation => return Ok(()),
+            Err(_) => return Err(FETCH_FAILED.to_string()),
+        };
         let response = response.dyn_into::<web_sys::Response>().unwrap();
 
         if !response.ok() {
@@ -154,13 +984,320 @@ impl AppLogic for Arc<App> {
             .ok_or("could not fetch scene data".to_string())?;
         let stream = wasm_streams::ReadableStream::from_raw(stream);
 
-        app.load_scene(stream.into_async_read()).await
+        app.load_scene(url, stream.into_async_read()).await
+    }
+
+    async fn switch_adapter(&self, request: display::AdapterRequest) -> Result<(), String> {
+        let app = self.clone();
+        let canvas = app
+            .window
+            .canvas()
+            .ok_or("could not find canvas".to_string())?;
+        let new_display = display::Display::from_canvas(&canvas, request).await?;
+
+        *app.display.borrow_mut() = Rc::new(new_display);
+        app.state.borrow_mut().adapter_request = request;
+
+        // The fresh egui renderer has never seen the font atlas, so upload it again.
+        let egui_ctx = app.state.borrow().gui_state.egui_ctx().clone();
+        let font_image = egui_ctx.fonts(|fonts| fonts.image());
+        egui_ctx.tex_manager().write().set(
+            egui::TextureId::default(),
+            egui::epaint::ImageDelta::full(
+                font_image,
+                egui::epaint::TextureAtlas::texture_options(),
+            ),
+        );
+
+        let old_tsplat = {
+            let state = &mut app.state.borrow_mut();
+            let display = app.display();
+            state.effective_supersample =
+                display.clamp_supersample(&state.render_resolution, state.supersample);
+            state.render_frame =
+                display.create_render_frame(&state.render_resolution, state.effective_supersample);
+            state.stale_camera = true;
+            let old_scene = state.scene.take().map(scene::Scene::into_tsplat);
+            old_scene.or_else(|| state.stranded_scene.take())
+        };
+
+        if let Some(old_tsplat) = old_tsplat {
+            let (pbar, generation) = {
+                let state = &mut app.state.borrow_mut();
+                state.begin_loading();
+                (state.progress_bar.clone(), state.load_generation)
+            };
+            pbar.update_status("re-uploading scene to new gpu".to_string())
+                .await;
+            let atlas_filter = app.state.borrow().atlas_filter;
+            let new_scene =
+                scene::Scene::upload(old_tsplat, &app.display(), atlas_filter, pbar).await;
+            let state = &mut app.state.borrow_mut();
+            // A load started meanwhile replaces the scene being moved.
+            if state.load_generation != generation {
+                return Ok(());
+            }
+            // Errors leave the state loading, so show_err reports them as a failed load.
+            let new_scene = new_scene.map_err(|(err, tsplat)| {
+                state.stranded_scene = Some(tsplat);
+                format!(
+                    "{}; switch back to the previous gpu to restore the scene",
+                    err
+                )
+            })?;
+            state.scene = Some(new_scene);
+            state.loading = false;
+            state.stale_camera = true;
+        }
+
+        Ok(())
+    }
+
+    fn load_file(&self, file: web_sys::File, handle: Option<JsValue>) {
+        {
+            let state = &mut self.state.borrow_mut();
+            state.begin_loading();
+            state.scene_source = Some(SceneSource::File { handle });
+        }
+
+        let name = file.name();
+        web_sys::console::log_1(&name.clone().into());
+
+        let stream = wasm_streams::ReadableStream::from_raw(file.stream());
+
+        let app = self.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(err_string) = app.load_scene(name, stream.into_async_read()).await {
+                app.show_err(err_string);
+            }
+        });
+    }
+
+    // Loads one of the dropped files. The current scene stays up until the next one has loaded,
+    // so a load that fails leaves it in place; the cost is that both scenes are resident on the
+    // GPU during the switch.
+    fn play(&self, index: usize) {
+        let file = {
+            let mut state = self.state.borrow_mut();
+            let Some(file) = state.playlist.get(index).cloned() else {
+                return;
+            };
+            state.playlist_index = index;
+            file
+        };
+        self.load_file(file, None);
+    }
+
+    async fn reload(&self) {
+        let source = {
+            let state = self.state.borrow();
+            if let Some(reason) = state.reload_unavailable() {
+                drop(state);
+                self.state.borrow_mut().show_transient(reason.to_string());
+                return;
+            }
+            match &state.scene_source {
+                Some(SceneSource::Url(url)) => SceneSource::Url(url.clone()),
+                Some(SceneSource::File { handle }) => SceneSource::File {
+                    handle: handle.clone(),
+                },
+                None => return,
+            }
+        };
+        match source {
+            SceneSource::Url(url) => {
+                self.state.borrow_mut().begin_loading();
+                if let Err(err_string) = self.load_url(url).await {
+                    self.show_err(err_string);
+                }
+            }
+            SceneSource::File {
+                handle: Some(handle),
+            } => match picker::file_from_handle(&handle).await {
+                Ok(file) => self.load_file(file, Some(handle)),
+                Err(err_string) => self.show_err(err_string),
+            },
+            SceneSource::File { handle: None } => (),
+        }
+    }
+
+    async fn open_file_picker(&self) {
+        match picker::pick_file().await {
+            Ok(Some(picked)) => {
+                self.state.borrow_mut().playlist.clear();
+                self.load_file(picked.file, picked.handle);
+            }
+            Ok(None) => (),
+            Err(err_string) => self.show_err(err_string),
+        }
+    }
+
+    async fn resort(&self, direction: Vec3f) {
+        let (generation, keys) = {
+            let state = self.state.borrow();
+            (
+                state.load_generation,
+                state
+                    .scene
+                    .as_ref()
+                    .map(|scene| scene.sort_keys(&direction)),
+            )
+        };
+        let Some(keys) = keys else {
+            self.state.borrow_mut().resort_pending = false;
+            return;
+        };
+        // Sort between frames rather than inside the redraw that noticed the turn.
+        utils::yield_async(0).await;
+        let mut order = (0..keys.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| keys[b].total_cmp(&keys[a]));
+
+        let mut state = self.state.borrow_mut();
+        state.resort_pending = false;
+        if state.load_generation == generation
+            && let Some(scene) = &mut state.scene
+            && scene.num_tris() == order.len()
+        {
+            scene.apply_order(&self.display().queue, &order, direction);
+            state.stale_camera = true;
+        }
+    }
+
+    async fn export_mask(&self) {
+        let pixels = {
+            let state = self.state.borrow();
+            self.display().read_frame(&state.render_frame)
+        };
+        let result = pixels.await.and_then(|pixels| {
+            export::download_png(
+                "mask.png",
+                pixels.width,
+                pixels.height,
+                &export::mask_rgba8(&pixels),
+            )
+        });
+        if let Err(err_string) = result {
+            self.show_err(err_string);
+        }
+    }
+
+    // Waits until the frame has accumulated its target samples for the current view.
+    async fn wait_for_converged_frame(&self) -> Result<(), String> {
+        loop {
+            {
+                let state = self.state.borrow();
+                if state.scene.is_none() && !state.loading {
+                    return Err("no scene loaded".to_string());
+                }
+                if state.scene.is_some()
+                    && !state.loading
+                    && !state.stale_camera
+                    && state.converged()
+                {
+                    return Ok(());
+                }
+            }
+            utils::yield_async(50).await;
+        }
+    }
+
+    async fn export_screenshot(&self) {
+        {
+            let state = &mut self.state.borrow_mut();
+            if state.stale_camera || !state.converged() {
+                state
+                    .show_transient("saving the screenshot once accumulation finishes".to_string());
+            }
+        }
+        if let Err(err_string) = self.wait_for_converged_frame().await {
+            self.show_err(err_string);
+            return;
+        }
+        let (pixels, exposure, tonemap, srgb_encode) = {
+            let state = self.state.borrow();
+            (
+                self.display().read_frame(&state.render_frame),
+                state.exposure,
+                state.tonemap,
+                state.srgb_encode,
+            )
+        };
+        let result = pixels.await.and_then(|pixels| {
+            export::download_png(
+                "screenshot.png",
+                pixels.width,
+                pixels.height,
+                &export::display_rgba8(&pixels, exposure, tonemap, srgb_encode),
+            )
+        });
+        if let Err(err_string) = result {
+            self.show_err(err_string);
+        }
+    }
+
+    async fn frame_hash(&self) -> Result<u64, String> {
+        self.wait_for_converged_frame().await?;
+        let pixels = {
+            let state = self.state.borrow();
+            self.display().read_frame(&state.render_frame)
+        };
+        Ok(export::frame_hash(&pixels.await?))
     }
 
+    // Commands from the host page, as objects or JSON strings. Anything unrecognized is ignored.
+    fn handle_command(&self, command: JsValue) {
+        let command = match command.as_string() {
+            Some(json) => match js_sys::JSON::parse(&json) {
+                Ok(command) => command,
+                Err(_) => return,
+            },
+            None => command,
+        };
+        let field = |name: &str| js_sys::Reflect::get(&command, &name.into()).ok();
+        let number = |name: &str| {
+            field(name)
+                .and_then(|value| value.as_f64())
+                .map(|value| value as f32)
+        };
+        match field("type").and_then(|kind| kind.as_string()).as_deref() {
+            Some("setCamera") => self.state.borrow_mut().set_orbit_pose(
+                number("azimuth"),
+                number("elevation"),
+                number("zoom"),
+                number("fov"),
+            ),
+            Some("pause") => self.state.borrow_mut().paused = true,
+            Some("resume") => self.state.borrow_mut().paused = false,
+            Some("loadUrl") => {
+                if let Some(url) = field("url").and_then(|url| url.as_string()) {
+                    let app = self.clone();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        app.state.borrow_mut().begin_loading();
+                        if let Err(err_string) = app.load_url(url).await {
+                            app.show_err(err_string);
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+        self.window.request_redraw();
+    }
+
+    // An error while a load is in progress means that load failed, which the host hears about.
     fn show_err(&self, err_string: String) {
-        let state = &mut self.state.borrow_mut();
-        state.loading = false;
-        state.error_message = Some(err_string);
+        let was_loading = {
+            let state = &mut self.state.borrow_mut();
+            let was_loading = state.loading;
+            state.loading = false;
+            state.error_message = Some(err_string.clone());
+            was_loading
+        };
+        if was_loading && let Some(canvas) = self.window.canvas() {
+            let detail = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&detail, &"message".into(), &err_string.into());
+            utils::dispatch_canvas_event(&canvas, "tsplat-error", &detail);
+        }
     }
 }
 
@@ -187,33 +1324,30 @@ impl ApplicationHandler for Handler {
 
         let handler = self.0.clone();
         let init_future = async move {
-            let app_orig = Arc::new(App::new(window, &canvas).await);
+            let app_orig = match App::new(window, &canvas).await {
+                Ok(app) => Arc::new(app),
+                Err(err_string) => {
+                    web_sys::console::error_1(&err_string.clone().into());
+                    let detail = js_sys::Object::new();
+                    let _ = js_sys::Reflect::set(&detail, &"message".into(), &err_string.into());
+                    utils::dispatch_canvas_event(&canvas, "tsplat-error", &detail);
+                    return;
+                }
+            };
             handler.borrow_mut().app = Some(app_orig.clone());
+            APP.set(Some(app_orig.clone()));
 
             let app = app_orig.clone();
             canvas.set_ondrop(Some(
                 Closure::<dyn FnMut(web_sys::DragEvent)>::new(move |ev: web_sys::DragEvent| {
                     ev.prevent_default();
                     let dt = ev.data_transfer().unwrap();
-                    let items = dt.items();
-                    if let Some(item) = items.get(0) {
-                        if let Ok(Some(file)) = item.get_as_file() {
-                            app.state.borrow_mut().begin_loading();
-
-                            let name = file.name();
-                            web_sys::console::log_1(&name.into());
-
-                            let stream = wasm_streams::ReadableStream::from_raw(file.stream());
-
-                            let app = app.clone();
-                            wasm_bindgen_futures::spawn_local(async move {
-                                if let Err(err_string) =
-                                    app.load_scene(stream.into_async_read()).await
-                                {
-                                    app.show_err(err_string);
-                                }
-                            });
-                        }
+                    let files = dt.files().map_or(vec![], |files| {
+                        (0..files.length()).filter_map(|i| files.get(i)).collect()
+                    });
+                    if !files.is_empty() {
+                        app.state.borrow_mut().playlist = files;
+                        app.play(0);
                     }
                 })
                 .into_js_value()
@@ -248,6 +1382,43 @@ impl ApplicationHandler for Handler {
                 .unchecked_ref(),
             ));
 
+            // Only the embedding page may drive the viewer; a top-level page is its own parent.
+            let app = app_orig.clone();
+            let parent = browser_window.parent().ok().flatten();
+            browser_window.set_onmessage(Some(
+                Closure::<dyn FnMut(web_sys::MessageEvent)>::new(
+                    move |ev: web_sys::MessageEvent| {
+                        let from_host = ev.source().is_some_and(|source| {
+                            parent
+                                .as_ref()
+                                .is_some_and(|parent| js_sys::Object::is(&source, parent))
+                        });
+                        if from_host {
+                            app.handle_command(ev.data());
+                        }
+                    },
+                )
+                .into_js_value()
+                .unchecked_ref(),
+            ));
+
+            if let Some(params) = utils::hash_params() {
+                app_orig.state.borrow_mut().apply_pose(&params);
+            }
+
+            // ?scene=<url> deep-links to a scene, ?autostart=1 skips the chooser without one.
+            if let Some(url) = utils::query_param("scene") {
+                let app = app_orig.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    app.state.borrow_mut().begin_loading();
+                    if let Err(err_string) = app.load_url(url).await {
+                        app.show_err(err_string);
+                    }
+                });
+            } else if utils::query_param("autostart").as_deref() == Some("1") {
+                app_orig.state.borrow_mut().chooser_open = false;
+            }
+
             app_orig.window.request_redraw();
         };
 
@@ -280,11 +1451,12 @@ impl ApplicationHandler for Handler {
                     if state.mouse_dragging {
                         let delta_x = new_x - state.prev_mouse_pos.0;
                         let delta_y = new_y - state.prev_mouse_pos.1;
-                        state.azimuth -= delta_x * 0.01;
-                        state.elevation += delta_y * 0.01;
-                        if delta_x.abs() > 0.1 || delta_y.abs() > 0.1 {
-                            state.stale_camera = true;
-                        }
+                        state.orbit(delta_x, delta_y);
+                    }
+                    if state.mouse_panning {
+                        let delta_x = new_x - state.prev_mouse_pos.0;
+                        let delta_y = new_y - state.prev_mouse_pos.1;
+                        state.pan_camera(delta_x, delta_y);
                     }
 
                     state.prev_mouse_pos = (new_x, new_y);
@@ -295,15 +1467,39 @@ impl ApplicationHandler for Handler {
                     phase: _,
                 } => {
                     let mut state = app.state.borrow_mut();
+                    // Some browsers turn shift+wheel into horizontal scrolling.
+                    let shift = state.modifiers.shift_key();
                     match delta {
-                        winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                            state.zoom *= 1.01f32.powf(y as f32);
+                        winit::event::MouseScrollDelta::LineDelta(x, y) => {
+                            let y = if shift && y == 0.0 { x } else { y };
+                            state.scroll_zoom(y);
                         }
                         winit::event::MouseScrollDelta::PixelDelta(pos) => {
-                            state.zoom *= 1.01f32.powf(-pos.y as f32 / 10.0);
+                            // Browsers report trackpad pinches as wheel events with ctrl held.
+                            let pinching = state.modifiers.control_key();
+                            if state.trackpad_mode == TrackpadMode::PanPinchZoom && !pinching {
+                                state.pan_camera(pos.x as f32, pos.y as f32);
+                            } else if pinching {
+                                state.pinch(1.01f32.powf(-pos.y as f32));
+                            } else {
+                                let y = if shift && pos.y == 0.0 { pos.x } else { pos.y };
+                                state.scroll_zoom(-y as f32 / 10.0);
+                            }
                         }
                     }
-                    state.stale_camera = true;
+                }
+                winit::event::WindowEvent::Touch(touch) => {
+                    app.state.borrow_mut().touch(touch);
+                }
+                winit::event::WindowEvent::PinchGesture {
+                    device_id: _,
+                    delta,
+                    phase: _,
+                } => {
+                    app.state.borrow_mut().pinch(1.0 / (1.0 + delta as f32));
+                }
+                winit::event::WindowEvent::ModifiersChanged(modifiers) => {
+                    app.state.borrow_mut().modifiers = modifiers.state();
                 }
                 winit::event::WindowEvent::MouseInput {
                     device_id: _,
@@ -313,30 +1509,175 @@ impl ApplicationHandler for Handler {
                     let mut appstate = app.state.borrow_mut();
                     if button == winit::event::MouseButton::Left {
                         if state.is_pressed() {
-                            appstate.mouse_dragging = true;
+                            if appstate.modifiers.shift_key() && appstate.scene.is_some() {
+                                appstate.box_select_start = Some(appstate.prev_mouse_pos);
+                            } else {
+                                appstate.mouse_dragging = true;
+                            }
                         } else {
                             appstate.mouse_dragging = false;
+                            if let Some(start) = appstate.box_select_start.take() {
+                                let end = appstate.prev_mouse_pos;
+                                appstate.select_box(
+                                    &app.display().queue,
+                                    start,
+                                    end,
+                                    app.window.inner_size(),
+                                );
+                            }
+                        }
+                    } else if button == winit::event::MouseButton::Middle {
+                        appstate.mouse_panning = state.is_pressed();
+                    }
+                }
+                winit::event::WindowEvent::KeyboardInput {
+                    device_id: _,
+                    event,
+                    is_synthetic: _,
+                } => {
+                    let mut state = app.state.borrow_mut();
+                    // Movement keys are held, so track them until released.
+                    if let PhysicalKey::Code(code) = event.physical_key
+                        && FLY_KEYS.contains(&code)
+                    {
+                        if !event.state.is_pressed() {
+                            state.fly_keys.remove(&code);
+                        } else if !state.gui_state.egui_ctx().wants_keyboard_input()
+                            && !state.modifiers.control_key()
+                        {
+                            state.fly_keys.insert(code);
+                        }
+                    }
+                    if event.state.is_pressed()
+                        && !state.gui_state.egui_ctx().wants_keyboard_input()
+                        && !state.modifiers.control_key()
+                        && let winit::keyboard::Key::Character(c) = &event.logical_key
+                    {
+                        match c.as_str() {
+                            "[" => state.nudge_exposure(-EXPOSURE_STEP),
+                            "]" => state.nudge_exposure(EXPOSURE_STEP),
+                            "," => state.nudge_fov(-FOV_STEP),
+                            "." => state.nudge_fov(FOV_STEP),
+                            "f" => state.zoom_to_fit(),
+                            "1" => state.reset_zoom(),
+                            "t" => state.toggle_resolution(),
+                            "r" => {
+                                let app = app.clone();
+                                wasm_bindgen_futures::spawn_local(async move {
+                                    app.reload().await;
+                                });
+                            }
+                            _ => (),
                         }
                     }
                 }
+                winit::event::WindowEvent::Focused(false) => {
+                    app.state.borrow_mut().fly_keys.clear();
+                }
+                winit::event::WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                    app.window.request_redraw();
+                }
                 winit::event::WindowEvent::RedrawRequested => {
+                    // A hidden or zero-sized canvas can't back a surface, so stop the loop
+                    // until a resize gives it a real size.
+                    let size = app.window.inner_size();
+                    if size.width == 0 || size.height == 0 {
+                        return;
+                    }
+                    let frame_start = Instant::now();
                     let prev_res = app.state.borrow().render_resolution.clone();
-                    let prev_supersample = app.state.borrow().supersample;
+                    let prev_supersample = app.state.borrow().effective_supersample;
                     let (platform_output, gui_render_data) = gui::show_gui(app);
                     app.state
                         .borrow_mut()
                         .gui_state
                         .handle_platform_output(&app.window, platform_output);
+                    app.state.borrow_mut().persist_settings();
 
-                    let new_res = app.state.borrow().render_resolution.clone();
-                    let new_supersample = app.state.borrow().supersample;
+                    let follow_canvas = matches!(
+                        app.state.borrow().render_resolution,
+                        RenderResolution::Auto(..)
+                    );
+                    if follow_canvas {
+                        let size = app.window.inner_size();
+                        app.state.borrow_mut().render_resolution =
+                            RenderResolution::Auto(size.width.max(1), size.height.max(1));
+                    }
+                    let requested_res = app.state.borrow().render_resolution.clone();
+                    let new_res = app.display().fit_resolution(&requested_res);
+                    if new_res != requested_res {
+                        let mut state = app.state.borrow_mut();
+                        if !follow_canvas {
+                            state.show_transient(format!(
+                                "{} exceeds this GPU's maximum texture size of {}, using {}",
+                                requested_res,
+                                app.display().max_texture_dimension(),
+                                new_res
+                            ));
+                        }
+                        state.render_resolution = new_res.clone();
+                    }
+                    let new_supersample = app
+                        .display()
+                        .clamp_supersample(&new_res, app.state.borrow().supersample);
                     if new_res != prev_res || new_supersample != prev_supersample {
-                        let new_frame = app.display.create_render_frame(&new_res, new_supersample);
+                        let mut state = app.state.borrow_mut();
+                        // Canvas-following resizes stay the same choice for toggling purposes.
+                        let same_choice = matches!(
+                            (&prev_res, &new_res),
+                            (RenderResolution::Auto(..), RenderResolution::Auto(..))
+                        );
+                        if new_res != prev_res && !same_choice {
+                            state.prev_render_resolution = Some(prev_res);
+                        }
+                        state.effective_supersample = new_supersample;
+                    }
+
+                    // Rebuilding the frame textures is expensive, so wait for the choice to settle.
+                    let rebuild = {
+                        let mut state = app.state.borrow_mut();
+                        if state.render_frame.fits(&new_res, new_supersample) {
+                            state.render_frame.resolution = new_res.clone();
+                            state.pending_frame = None;
+                            false
+                        } else {
+                            match &state.pending_frame {
+                                Some((res, supersample, since))
+                                    if *res == new_res && *supersample == new_supersample =>
+                                {
+                                    since.elapsed().as_secs_f32() >= FRAME_REBUILD_DELAY_SECS
+                                }
+                                _ => {
+                                    state.pending_frame =
+                                        Some((new_res.clone(), new_supersample, frame_start));
+                                    false
+                                }
+                            }
+                        }
+                    };
+                    if rebuild {
+                        let new_frame =
+                            app.display().create_render_frame(&new_res, new_supersample);
                         let mut state = app.state.borrow_mut();
                         state.render_frame = new_frame;
+                        state.pending_frame = None;
                         state.stale_camera = true;
                     }
 
+                    {
+                        let mut state = app.state.borrow_mut();
+                        let frame_time = state.avg_frame_time * state.subframe_count as f64;
+                        state.fly(frame_time as f32);
+                        state.spin_turntable(frame_time as f32);
+                        state.clamp_zoom_outside();
+                        if let Some(direction) = state.resort_direction() {
+                            let app = app.clone();
+                            wasm_bindgen_futures::spawn_local(async move {
+                                app.resort(direction).await;
+                            });
+                        }
+                    }
+
                     let paused = app.state.borrow().paused;
                     if let Some(scene) = &mut app.state.borrow_mut().scene {
                         if !paused {
@@ -349,7 +1690,7 @@ impl ApplicationHandler for Handler {
                     let stale_camera = app.state.borrow().stale_camera;
                     let mut state = app.state.borrow_mut();
                     state.stale_camera = false;
-                    app.display.render(
+                    let first_scene_frame = app.display().render(
                         gui_render_data,
                         &mut state,
                         canvas_width,
@@ -358,8 +1699,30 @@ impl ApplicationHandler for Handler {
                         stale_camera,
                     );
 
+                    // Sleep off the rest of the frame budget; the subframe heuristic excludes it.
+                    // The placeholder animation needs no more than a low frame rate.
+                    let fps_cap = match state.fps_cap {
+                        _ if state.scene.is_some() => state.fps_cap,
+                        Some(cap) => Some(cap.min(PLACEHOLDER_FPS)),
+                        None => Some(PLACEHOLDER_FPS),
+                    };
+                    let throttle_ms = fps_cap.map_or(0, |cap| {
+                        let elapsed = frame_start.elapsed().as_secs_f64();
+                        ((1.0 / cap as f64 - elapsed) * 1000.0).max(0.0) as i32
+                    });
+                    state.throttle_time = throttle_ms as f64 / 1000.0;
+                    std::mem::drop(state);
+
+                    // Listeners may call back into the viewer, so the state is released first.
+                    if first_scene_frame && let Some(canvas) = app.window.canvas() {
+                        utils::dispatch_canvas_event(&canvas, "tsplat-ready", &JsValue::NULL);
+                    }
+
                     let app = app.clone();
                     wasm_bindgen_futures::spawn_local(async move {
+                        if throttle_ms > 0 {
+                            utils::yield_async(throttle_ms).await;
+                        }
                         app.window.request_redraw();
                     });
                 }
@@ -369,9 +1732,237 @@ impl ApplicationHandler for Handler {
     }
 }
 
+fn touch_centroid_spread(touches: &HashMap<u64, (f32, f32)>) -> ((f32, f32), f32) {
+    let count = touches.len().max(1) as f32;
+    let (sum_x, sum_y) = touches
+        .values()
+        .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    let centroid = (sum_x / count, sum_y / count);
+    let spread = touches
+        .values()
+        .map(|(x, y)| ((x - centroid.0).powi(2) + (y - centroid.1).powi(2)).sqrt())
+        .sum::<f32>()
+        / count;
+    (centroid, spread)
+}
+
+fn current_app() -> Result<Arc<App>, JsValue> {
+    APP.with_borrow(|app| app.clone())
+        .ok_or_else(|| JsValue::from_str("viewer is not initialized"))
+}
+
+#[wasm_bindgen]
+pub async fn frame_hash() -> Result<String, JsValue> {
+    let hash = current_app()?.frame_hash().await?;
+    Ok(format!("{:016x}", hash))
+}
+
+#[wasm_bindgen]
+pub fn orbit_center() -> Result<Option<Vec<f32>>, JsValue> {
+    let center = current_app()?.state.borrow().orbit_center();
+    Ok(center.map(|center| center.as_slice().to_vec()))
+}
+
+#[wasm_bindgen]
+pub fn set_orbit_center(x: f32, y: f32, z: f32) -> Result<(), JsValue> {
+    current_app()?
+        .state
+        .borrow_mut()
+        .set_orbit_center(Vec3f::new(x, y, z));
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn unload() -> Result<(), JsValue> {
+    current_app()?.state.borrow_mut().unload();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn reset_accumulation() -> Result<(), JsValue> {
+    current_app()?.state.borrow_mut().reset_accumulation();
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn set_theme(theme: &JsValue) -> Result<(), JsValue> {
+    let theme = gui::ThemeOverride::from_js(theme)?;
+    current_app()?.state.borrow_mut().theme = theme;
+    Ok(())
+}
+
+fn scene_data<F>(flatten: F) -> Result<Option<Vec<f32>>, JsValue>
+where
+    F: FnOnce(&load::TSplat) -> Vec<f32>,
+{
+    let app = current_app()?;
+    let state = app.state.borrow();
+    Ok(state.scene.as_ref().map(|scene| flatten(scene.tsplat())))
+}
+
+#[wasm_bindgen]
+pub fn scene_positions() -> Result<Option<Vec<f32>>, JsValue> {
+    scene_data(|tsplat| {
+        tsplat
+            .points
+            .iter()
+            .flat_map(|tri| tri.iter().flat_map(|v| [v.x, v.y, v.z]))
+            .collect()
+    })
+}
+
+#[wasm_bindgen]
+pub fn scene_alpha_sigma() -> Result<Option<Vec<f32>>, JsValue> {
+    scene_data(|tsplat| {
+        tsplat
+            .alpha_sigma
+            .iter()
+            .flat_map(|v| [v.x.to_f32(), v.y.to_f32()])
+            .collect()
+    })
+}
+
+#[wasm_bindgen]
+pub fn scene_sh() -> Result<Option<Vec<f32>>, JsValue> {
+    scene_data(|tsplat| {
+        tsplat
+            .sh
+            .iter()
+            .flat_map(|v| [v.x.to_f32(), v.y.to_f32(), v.z.to_f32(), v.w.to_f32()])
+            .collect()
+    })
+}
+
+// Renders scene bytes offscreen and resolves to PNG bytes, with no window, panel or event loop.
+#[wasm_bindgen]
+pub async fn render_thumbnail(scene_bytes: Vec<u8>, options: JsValue) -> Result<Vec<u8>, JsValue> {
+    let option = |name: &str| number_option(&options, name);
+    let width = option("width")
+        .map_or(THUMBNAIL_SIZE, |width| width as u32)
+        .max(1);
+    let height = option("height")
+        .map_or(THUMBNAIL_SIZE, |height| height as u32)
+        .max(1);
+    let samples = option("samples")
+        .map_or(THUMBNAIL_SAMPLES, |samples| samples as u32)
+        .clamp(1, MAX_THUMBNAIL_SAMPLES);
+    let camera = scene::Camera {
+        azimuth: option("azimuth").unwrap_or(DEFAULT_AZIMUTH),
+        elevation: option("elevation")
+            .unwrap_or(DEFAULT_ELEVATION)
+            .clamp(-MAX_ELEVATION, MAX_ELEVATION),
+        zoom: option("zoom")
+            .filter(|zoom| *zoom > 0.0)
+            .unwrap_or(DEFAULT_ZOOM),
+        fov: option("fov").unwrap_or(DEFAULT_FOV).clamp(MIN_FOV, MAX_FOV),
+        pan_offset: Vec3f::zeros(),
+        translation: Vec3f::zeros(),
+    };
+
+    let display = offscreen_display().await?;
+    let max_dimension = display.device.limits().max_texture_dimension_2d;
+    if width > max_dimension || height > max_dimension {
+        return Err(
+            format!("thumbnail is larger than the gpu's {max_dimension} pixel limit").into(),
+        );
+    }
+    let mut scene = offscreen_scene(&display, scene_bytes).await?;
+    // Without a GPU sort the file order only suits views near the load-time sort axis.
+    let (_, _, forward) = scene.view_basis(&camera);
+    if !scene.has_gpu_sort() && forward.angle(&scene.sort_direction()) >= RESORT_ANGLE {
+        let keys = scene.sort_keys(&forward);
+        let mut order = (0..keys.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| keys[b].total_cmp(&keys[a]));
+        scene.apply_order(&display.queue, &order, forward);
+    }
+
+    let resolution = display::RenderResolution::Native(width, height);
+    let mut frame = display.create_render_frame(&resolution, 1);
+    let settings = display::AccumulateSettings {
+        camera,
+        background: DEFAULT_BACKGROUND,
+        mask_mode: false,
+        fixed_seed: true,
+        target_samples: samples,
+        denoise: false,
+        denoise_strength: 0.0,
+        selection: scene::SelectionMode::Highlight,
+        alpha_scale: 1.0,
+        sigma_scale: 1.0,
+        show_grid: false,
+    };
+    // The first sample replaces the cleared history; the rest average into it.
+    let mut accumulated_samples = 0;
+    display.accumulate(
+        &scene,
+        &mut frame,
+        &settings,
+        &mut accumulated_samples,
+        1,
+        true,
+    );
+    display.accumulate(
+        &scene,
+        &mut frame,
+        &settings,
+        &mut accumulated_samples,
+        samples - 1,
+        false,
+    );
+    let pixels = display.read_frame(&frame).await?;
+    let rgba8 = export::display_rgba8(&pixels, 0.0, display::Tonemap::None, false);
+    Ok(export::encode_png(pixels.width, pixels.height, &rgba8).await?)
+}
+
+fn number_option(options: &JsValue, name: &str) -> Option<f32> {
+    js_sys::Reflect::get(options, &name.into())
+        .ok()
+        .and_then(|value| value.as_f64())
+        .filter(|value| value.is_finite())
+        .map(|value| value as f32)
+}
+
+async fn offscreen_scene(
+    display: &display::Display,
+    scene_bytes: Vec<u8>,
+) -> Result<scene::Scene, String> {
+    let tsplat = load::read_tsplat(
+        futures::io::Cursor::new(scene_bytes),
+        pbar::make_progress_bar(),
+        false,
+        |_| {},
+    )
+    .await?;
+    scene::Scene::new(
+        tsplat,
+        display,
+        wgpu::FilterMode::Nearest,
+        pbar::make_progress_bar(),
+    )
+    .await
+}
+
+async fn offscreen_display() -> Result<Rc<display::Display>, String> {
+    if let Some(display) = OFFSCREEN_DISPLAY.with_borrow(|display| display.clone()) {
+        return Ok(display);
+    }
+    let display = Rc::new(display::Display::offscreen(display::AdapterRequest::default()).await?);
+    OFFSCREEN_DISPLAY.set(Some(display.clone()));
+    Ok(display)
+}
+
+#[wasm_bindgen]
+pub fn sort_axis() -> Vec<f32> {
+    load::SORT_AXIS.to_vec()
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub fn run() {
     console_error_panic_hook::set_once();
+    // The scene parser worker loads this module too, but has no canvas to run the viewer in.
+    if web_sys::window().is_none() {
+        return;
+    }
     let event_loop = EventLoop::new().unwrap();
     let handler = Handler(Arc::new(RefCell::new(HandlerInner { app: None })));
     event_loop.spawn_app(handler);
@@ -1,38 +1,177 @@
-use futures::{AsyncBufReadExt, AsyncRead, AsyncReadExt, io::BufReader};
+use async_compression::futures::bufread::GzipDecoder;
+use futures::{
+    AsyncBufReadExt, AsyncRead, AsyncReadExt,
+    io::{BufReader, Cursor},
+};
 use half::f16;
 use web_time::Instant;
 
 use crate::{
-    pbar::{Progress, ProgressBar},
+    pbar::{Progress, ProgressBar, make_progress_bar},
     utils::{Vec2h, Vec3f, Vec3h, Vec4h},
 };
 
 const INVALID_FLIE: &str = "invalid model file";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+pub const OUT_OF_MEMORY: &str =
+    "out of memory loading scene — try lowering resolution or a smaller file";
+// Far beyond real scenes, low enough that the byte counts below fit a wasm32 usize.
+const MAX_TRIS: usize = 1 << 24;
+const DEGENERATE_AREA_EPSILON: f32 = 1e-12;
+pub const SORT_AXIS: [f32; 3] = [0.8644, 0.4385, 0.2458];
+// Degree-3 SH is 16 rgb coefficients, packed into 12 rgba texels per triangle.
+const SH_BLOCKS: usize = 12;
+
+const FORMAT_VERSION: u8 = 1;
+const FLAG_FULL_SH: u8 = 1 << 0;
+const FLAG_Y_UP: u8 = 1 << 1;
+
+// Files without a version byte are version 0: DC-only and Y-down unless the header says SH.
+#[derive(Clone, Copy)]
+pub struct Format {
+    pub version: u8,
+    pub full_sh: bool,
+    pub y_up: bool,
+    pub up: Option<Vec3f>,
+}
 
 pub struct TSplat {
     pub points: Vec<[Vec3f; 3]>,
     pub alpha_sigma: Vec<Vec2h>,
     pub sh: Vec<Vec4h>,
+    pub format: Format,
+    pub incomplete: bool,
+    // Axis-aligned min and max over the vertices of the kept triangles.
+    pub bounds: (Vec3f, Vec3f),
+}
+
+// A corrupt gzip stream fails with InvalidData, which deserves its own message.
+fn read_error(err: std::io::Error) -> String {
+    match err.kind() {
+        std::io::ErrorKind::InvalidData => format!("could not decompress model file: {}", err),
+        _ => INVALID_FLIE.to_string(),
+    }
 }
 
-pub async fn read_tsplat<S: AsyncRead + Unpin>(
+pub async fn read_tsplat<S, F>(
     byte_stream: S,
     pbar: ProgressBar,
-) -> Result<TSplat, String> {
+    filter_degenerate: bool,
+    on_preview: F,
+) -> Result<TSplat, String>
+where
+    S: AsyncRead + Unpin,
+    F: FnOnce(TSplat),
+{
     pbar.update_status("downloading model".to_string()).await;
 
+    // Gzipped files are recognized by their magic bytes, whatever they are named. Servers that send
+    // Content-Encoding: gzip instead are decoded by the browser and arrive here uncompressed.
+    let mut byte_stream = byte_stream;
+    let mut magic = [0u8; 2];
+    byte_stream
+        .read_exact(&mut magic)
+        .await
+        .map_err(|_| INVALID_FLIE)?;
+    let gzip = magic == GZIP_MAGIC;
+    let byte_stream = Cursor::new(magic).chain(byte_stream);
+    let byte_stream: Box<dyn AsyncRead + Unpin> = if gzip {
+        let mut decoder = GzipDecoder::new(BufReader::with_capacity(1 << 20, byte_stream));
+        decoder.multiple_members(true);
+        Box::new(decoder)
+    } else {
+        Box::new(byte_stream)
+    };
     let mut reader = BufReader::with_capacity(1 << 20, byte_stream);
 
     let mut current_line = String::new();
     reader
         .read_line(&mut current_line)
         .await
-        .map_err(|_| INVALID_FLIE)?;
+        // A binary file that isn't gzip also fails here, as invalid UTF-8.
+        .map_err(|err| {
+            if gzip {
+                read_error(err)
+            } else {
+                INVALID_FLIE.to_string()
+            }
+        })?;
 
-    if current_line.trim() != "TSPLAT" {
+    // The header is "TSPLAT" followed by optional PREVIEW, SH, VERSIONED and UP tokens.
+    let mut tokens = current_line.split_whitespace();
+    if tokens.next() != Some("TSPLAT") {
         return Err(INVALID_FLIE.into());
     }
+    let (mut has_preview, mut versioned, mut has_up) = (false, false, false);
+    let mut format = Format {
+        version: 0,
+        full_sh: false,
+        y_up: false,
+        up: None,
+    };
+    for token in tokens {
+        match token {
+            "PREVIEW" => has_preview = true,
+            // Files without this flag carry only the DC term, to fit within github limits.
+            "SH" => format.full_sh = true,
+            "VERSIONED" => versioned = true,
+            "UP" => has_up = true,
+            _ => return Err(INVALID_FLIE.into()),
+        }
+    }
+
+    if versioned {
+        // A version byte and a flags bitfield follow the magic line.
+        let mut version_flags = [0u8; 2];
+        reader
+            .read_exact(&mut version_flags)
+            .await
+            .map_err(|_| INVALID_FLIE)?;
+        let [version, flags] = version_flags;
+        if version > FORMAT_VERSION {
+            return Err(format!("unsupported TSPLAT version {}", version));
+        }
+        format.version = version;
+        format.full_sh |= flags & FLAG_FULL_SH != 0;
+        format.y_up = flags & FLAG_Y_UP != 0;
+    }
+
+    if has_up {
+        // Three little-endian f32s giving the world up direction, which overrides FLAG_Y_UP.
+        let mut up_bytes = [0u8; 12];
+        reader
+            .read_exact(&mut up_bytes)
+            .await
+            .map_err(|_| INVALID_FLIE)?;
+        let up = Vec3f::from_iterator(
+            up_bytes
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap())),
+        );
+        let norm = up.norm();
+        if !norm.is_normal() {
+            return Err("invalid up vector in TSPLAT header".to_string());
+        }
+        format.up = Some(up / norm);
+    }
+
+    if has_preview {
+        // A small decimated triangle set precedes the full data so it can be shown early.
+        let preview =
+            read_triangles(&mut reader, &make_progress_bar(), filter_degenerate, format).await?;
+        on_preview(preview);
+    }
 
+    read_triangles(&mut reader, &pbar, filter_degenerate, format).await
+}
+
+async fn read_triangles<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    pbar: &ProgressBar,
+    filter_degenerate: bool,
+    format: Format,
+) -> Result<TSplat, String> {
+    let full_sh = format.full_sh;
     let mut num_tris_bytes = [0u8; 4];
     reader
         .read_exact(&mut num_tris_bytes)
@@ -41,76 +180,184 @@ pub async fn read_tsplat<S: AsyncRead + Unpin>(
     let num_tris = u32::from_le_bytes(num_tris_bytes) as usize;
 
     web_sys::console::log_1(&format!("num_tris: {}", num_tris).into());
+    if num_tris > MAX_TRIS {
+        return Err(format!(
+            "file claims {} triangles, more than the limit of {}; it is probably corrupt",
+            num_tris, MAX_TRIS
+        ));
+    }
+    if num_tris == 0 {
+        return Err("the file is a valid TSPLAT but contains no triangles".to_string());
+    }
 
     let points_bytes = num_tris * 3 * std::mem::size_of::<Vec3f>();
     let alpha_sigma_bytes = num_tris * std::mem::size_of::<Vec2h>();
-    // let sh_bytes = num_tris * 12 * std::mem::size_of::<Vec4h>();
-    let sh_bytes = num_tris * std::mem::size_of::<Vec3h>();
+    let sh_texel_bytes = if full_sh {
+        std::mem::size_of::<Vec4h>()
+    } else {
+        std::mem::size_of::<Vec3h>()
+    };
+    let sh_bytes = if full_sh {
+        num_tris * SH_BLOCKS * sh_texel_bytes
+    } else {
+        num_tris * sh_texel_bytes
+    };
+    // Progress is measured against the decoded size: browsers undo any Content-Encoding before
+    // the body reaches us, so the transfer size (Content-Length) would not match.
     let expected_bytes = points_bytes + alpha_sigma_bytes + sh_bytes;
 
-    let mut buffer = vec![0u8; expected_bytes];
+    let mut buffer = Vec::new();
+    buffer
+        .try_reserve_exact(expected_bytes)
+        .map_err(|_| OUT_OF_MEMORY)?;
+    buffer.resize(expected_bytes, 0u8);
     let mut bytes_read = 0;
     let mut last_update_time = Instant::now();
+    pbar.start_bytes(expected_bytes as u64);
 
     while bytes_read < expected_bytes {
         let read = reader
             .read(&mut buffer[bytes_read..])
             .await
-            .map_err(|_| INVALID_FLIE)?;
+            .map_err(read_error)?;
         if read == 0 {
-            return Err(INVALID_FLIE.into());
+            break;
         }
         bytes_read += read;
 
         let now = Instant::now();
         if now.duration_since(last_update_time).as_millis() > 20 {
             let progress = bytes_read as f32 / expected_bytes as f32;
+            pbar.update_bytes(bytes_read as u64);
             pbar.update_progress(0.6 * progress).await;
             last_update_time = now;
         }
     }
+    pbar.finish_bytes();
+    pbar.update_progress(0.6 * bytes_read as f32 / expected_bytes.max(1) as f32)
+        .await;
+
+    // The colour blocks come last and are planar, so a truncated stream still holds every
+    // triangle whose texel in the last block arrived. When that is none of them, keep those with
+    // a DC colour and leave the higher-order coefficients that never arrived at zero.
+    let incomplete = bytes_read < expected_bytes;
+    let complete_tris = if incomplete {
+        let sh_bytes_read = bytes_read.saturating_sub(points_bytes + alpha_sigma_bytes);
+        let texels_read = sh_bytes_read / sh_texel_bytes;
+        let sh_blocks = if full_sh { SH_BLOCKS } else { 1 };
+        let whole_tris = texels_read.saturating_sub((sh_blocks - 1) * num_tris);
+        if whole_tris > 0 {
+            whole_tris.min(num_tris)
+        } else {
+            texels_read.min(num_tris)
+        }
+    } else {
+        num_tris
+    };
+    if complete_tris == 0 {
+        return Err(format!(
+            "file truncated: expected {} bytes of triangle data, got {}",
+            expected_bytes, bytes_read
+        ));
+    }
+    if incomplete {
+        let missing = if complete_tris < num_tris {
+            format!("keeping {} of {} triangles", complete_tris, num_tris)
+        } else {
+            "higher-order colour coefficients are missing".to_string()
+        };
+        web_sys::console::warn_1(
+            &format!(
+                "stream ended early after {} of {} bytes, {}",
+                bytes_read, expected_bytes, missing
+            )
+            .into(),
+        );
+    }
 
     pbar.update_status("parsing file".to_string()).await;
 
     let mut bytes_parsed = 0;
 
-    let points: Vec<[Vec3f; 3]> =
+    let mut points: Vec<[Vec3f; 3]> =
         bytemuck::cast_slice(&buffer[bytes_parsed..bytes_parsed + points_bytes]).to_vec();
     bytes_parsed += points_bytes;
 
-    let alpha_sigma: Vec<Vec2h> =
+    let mut alpha_sigma: Vec<Vec2h> =
         bytemuck::cast_slice(&buffer[bytes_parsed..bytes_parsed + alpha_sigma_bytes]).to_vec();
     bytes_parsed += alpha_sigma_bytes;
 
-    // let sh: Vec<Vec4h> =
-    //     bytemuck::cast_slice(&buffer[bytes_parsed..bytes_parsed + sh_bytes]).to_vec();
-
-    // To fit within github limits, we only load DC terms for SH and set others to zero.
-    let dc: Vec<Vec3h> =
-        bytemuck::cast_slice(&buffer[bytes_parsed..bytes_parsed + sh_bytes]).to_vec();
-    let sh = dc
-        .into_iter()
-        .map(|v| Vec4h::new(v.x, v.y, v.z, f16::from_f32(0.0)))
-        .chain(std::iter::repeat(Vec4h::new(
-            f16::from_f32(0.0),
-            f16::from_f32(0.0),
-            f16::from_f32(0.0),
-            f16::from_f32(0.0),
-        )))
-        .take(num_tris * 12)
-        .collect::<Vec<_>>();
+    let sh_buffer = &buffer[bytes_parsed..bytes_parsed + sh_bytes];
+    let declared_tris = num_tris;
+    let num_tris = complete_tris;
+    points.truncate(num_tris);
+    alpha_sigma.truncate(num_tris);
+
+    // Texels are planar: block k holds texel k of every triangle, as splatting.wgsl reads them.
+    let sh = if full_sh {
+        let texels: &[Vec4h] = bytemuck::cast_slice(sh_buffer);
+        (0..SH_BLOCKS)
+            .flat_map(|k| {
+                texels[k * declared_tris..k * declared_tris + num_tris]
+                    .iter()
+                    .copied()
+            })
+            .collect::<Vec<_>>()
+    } else {
+        let dc: &[Vec3h] = bytemuck::cast_slice(sh_buffer);
+        dc[..num_tris]
+            .iter()
+            .map(|v| Vec4h::new(v.x, v.y, v.z, f16::from_f32(0.0)))
+            .chain(std::iter::repeat(Vec4h::new(
+                f16::from_f32(0.0),
+                f16::from_f32(0.0),
+                f16::from_f32(0.0),
+                f16::from_f32(0.0),
+            )))
+            .take(num_tris * SH_BLOCKS)
+            .collect::<Vec<_>>()
+    };
 
     pbar.update_status("done parsing".to_string()).await;
 
-    let forward = Vec3f::new(0.8644, 0.4385, 0.2458);
-    let mut kv: Vec<(f32, usize)> = points
-        .iter()
-        .map(|tri| {
-            let c = tri[0] + tri[1] + tri[2] / 3.0;
-            c.dot(&forward)
-        })
-        .zip(0..)
-        .collect();
+    let forward = Vec3f::from(SORT_AXIS);
+    let mut kv: Vec<(f32, usize)> = Vec::with_capacity(num_tris);
+    let mut bounds = (
+        Vec3f::repeat(f32::INFINITY),
+        Vec3f::repeat(f32::NEG_INFINITY),
+    );
+    for (idx, tri) in points.iter().enumerate() {
+        if filter_degenerate && is_degenerate(tri) {
+            continue;
+        }
+        for vertex in tri {
+            bounds = (bounds.0.inf(vertex), bounds.1.sup(vertex));
+        }
+        let c = (tri[0] + tri[1] + tri[2]) / 3.0;
+        kv.push((c.dot(&forward), idx));
+    }
+    if kv.is_empty() {
+        bounds = (Vec3f::zeros(), Vec3f::zeros());
+    }
+
+    if kv.len() < num_tris {
+        let removed = num_tris - kv.len();
+        web_sys::console::log_1(&format!("removed {} degenerate triangles", removed).into());
+        pbar.update_status(format!("removed {} degenerate triangles", removed))
+            .await;
+    }
+
+    // Files pre-sorted along `SORT_AXIS` can skip the reorder.
+    if kv.len() == num_tris && kv.is_sorted_by(|a, b| a.0 >= b.0) {
+        return Ok(TSplat {
+            points,
+            alpha_sigma,
+            sh,
+            format,
+            incomplete,
+            bounds,
+        });
+    }
     kv.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
 
     let points_sorted = kv.iter().map(|(_, idx)| points[*idx]).collect::<Vec<_>>();
@@ -118,11 +365,22 @@ pub async fn read_tsplat<S: AsyncRead + Unpin>(
         .iter()
         .map(|(_, idx)| alpha_sigma[*idx])
         .collect::<Vec<_>>();
-    let sh_sorted = kv.iter().map(|(_, idx)| sh[*idx]).collect::<Vec<_>>();
+    let sh = &sh;
+    let sh_sorted = (0..SH_BLOCKS)
+        .flat_map(|k| kv.iter().map(move |(_, idx)| sh[k * num_tris + idx]))
+        .collect::<Vec<_>>();
 
     Ok(TSplat {
         points: points_sorted,
         alpha_sigma: alpha_sigma_sorted,
         sh: sh_sorted,
+        format,
+        incomplete,
+        bounds,
     })
 }
+
+fn is_degenerate(tri: &[Vec3f; 3]) -> bool {
+    let area = 0.5 * (tri[1] - tri[0]).cross(&(tri[2] - tri[0])).norm();
+    area.is_nan() || area <= DEGENERATE_AREA_EPSILON
+}
@@ -1,10 +1,15 @@
-use std::{cell::RefCell, sync::Arc};
+use std::sync::{Arc, Mutex};
+
+use web_time::Instant;
 
 use crate::utils::yield_async;
 
 pub struct PBar {
     progress: f32,
     status: String,
+    bytes_done: u64,
+    bytes_total: Option<u64>,
+    started_at: Option<Instant>,
 }
 
 pub trait Progress: Clone {
@@ -19,41 +24,90 @@ pub trait Progress: Clone {
     fn get_progress(&self) -> f32;
 
     fn get_status(&self) -> String;
+
+    fn start_bytes(&self, total: u64);
+
+    fn update_bytes(&self, done: u64);
+
+    fn finish_bytes(&self);
+
+    // Bytes done and expected, while a transfer is running.
+    fn get_bytes(&self) -> Option<(u64, u64)>;
+
+    // Bytes per second so far and the seconds left at that rate, while a transfer is running.
+    fn get_rate_eta(&self) -> Option<(f64, f64)>;
 }
 
-pub type ProgressBar = Arc<RefCell<PBar>>;
+// A Mutex rather than a RefCell keeps progress reportable from workers or other threads.
+pub type ProgressBar = Arc<Mutex<PBar>>;
 
 impl Progress for ProgressBar {
     async fn update_progress(&self, progress: f32) {
-        self.borrow_mut().progress = progress;
+        self.lock().unwrap().progress = progress;
         yield_async(0).await;
     }
 
     async fn update_status(&self, status: String) {
-        self.borrow_mut().status = status;
+        self.lock().unwrap().status = status;
         yield_async(0).await;
     }
 
     fn update_progress_sync(&self, progress: f32) {
-        self.borrow_mut().progress = progress;
+        self.lock().unwrap().progress = progress;
     }
 
     fn update_status_sync(&self, status: String) {
-        self.borrow_mut().status = status;
+        self.lock().unwrap().status = status;
     }
 
     fn get_progress(&self) -> f32 {
-        self.borrow().progress
+        self.lock().unwrap().progress
     }
 
     fn get_status(&self) -> String {
-        self.borrow().status.clone()
+        self.lock().unwrap().status.clone()
+    }
+
+    fn start_bytes(&self, total: u64) {
+        let mut pbar = self.lock().unwrap();
+        pbar.bytes_done = 0;
+        pbar.bytes_total = Some(total);
+        pbar.started_at = Some(Instant::now());
+    }
+
+    fn update_bytes(&self, done: u64) {
+        self.lock().unwrap().bytes_done = done;
+    }
+
+    fn finish_bytes(&self) {
+        let mut pbar = self.lock().unwrap();
+        pbar.bytes_total = None;
+        pbar.started_at = None;
+    }
+
+    fn get_bytes(&self) -> Option<(u64, u64)> {
+        let pbar = self.lock().unwrap();
+        Some((pbar.bytes_done, pbar.bytes_total?))
+    }
+
+    fn get_rate_eta(&self) -> Option<(f64, f64)> {
+        let pbar = self.lock().unwrap();
+        let elapsed = pbar.started_at?.elapsed().as_secs_f64();
+        let total = pbar.bytes_total?;
+        if elapsed <= 0.0 || pbar.bytes_done == 0 {
+            return None;
+        }
+        let rate = pbar.bytes_done as f64 / elapsed;
+        Some((rate, total.saturating_sub(pbar.bytes_done) as f64 / rate))
     }
 }
 
 pub fn make_progress_bar() -> ProgressBar {
-    Arc::new(RefCell::new(PBar {
+    Arc::new(Mutex::new(PBar {
         progress: 0.0,
         status: "".to_string(),
+        bytes_done: 0,
+        bytes_total: None,
+        started_at: None,
     }))
 }
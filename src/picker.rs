@@ -0,0 +1,100 @@
+use js_sys::{Array, Function, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use wasm_bindgen_futures::JsFuture;
+
+const ACCEPTED_EXTENSIONS: &str = ".tsplat";
+
+pub struct PickedFile {
+    pub file: web_sys::File,
+    // Only the File System Access API gives a handle that can be re-read later.
+    pub handle: Option<JsValue>,
+}
+
+pub async fn pick_file() -> Result<Option<PickedFile>, String> {
+    let window = web_sys::window().unwrap();
+    let show_open_file_picker = Reflect::get(&window, &"showOpenFilePicker".into())
+        .ok()
+        .and_then(|picker| picker.dyn_into::<Function>().ok());
+
+    match show_open_file_picker {
+        Some(show_open_file_picker) => {
+            pick_file_with_file_system_access(&window, &show_open_file_picker).await
+        }
+        None => Ok(pick_file_with_input()
+            .await?
+            .map(|file| PickedFile { file, handle: None })),
+    }
+}
+
+async fn pick_file_with_file_system_access(
+    window: &web_sys::Window,
+    show_open_file_picker: &Function,
+) -> Result<Option<PickedFile>, String> {
+    let promise = show_open_file_picker
+        .call0(window)
+        .map_err(|_| "could not open file picker".to_string())?;
+    let handles = match JsFuture::from(Promise::from(promise)).await {
+        Ok(handles) => handles,
+        // The picker rejects when the user dismisses it.
+        Err(_) => return Ok(None),
+    };
+
+    let handle = Array::from(&handles).get(0);
+    let file = file_from_handle(&handle).await?;
+    Ok(Some(PickedFile {
+        file,
+        handle: Some(handle),
+    }))
+}
+
+pub async fn file_from_handle(handle: &JsValue) -> Result<web_sys::File, String> {
+    let get_file = Reflect::get(handle, &"getFile".into())
+        .ok()
+        .and_then(|get_file| get_file.dyn_into::<Function>().ok())
+        .ok_or("could not read selected file".to_string())?;
+    let promise = get_file
+        .call0(handle)
+        .map_err(|_| "could not read selected file".to_string())?;
+    let file = JsFuture::from(Promise::from(promise))
+        .await
+        .map_err(|_| "could not read selected file".to_string())?;
+
+    file.dyn_into::<web_sys::File>()
+        .map_err(|_| "could not read selected file".to_string())
+}
+
+async fn pick_file_with_input() -> Result<Option<web_sys::File>, String> {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let input = document
+        .create_element("input")
+        .ok()
+        .and_then(|input| input.dyn_into::<web_sys::HtmlInputElement>().ok())
+        .ok_or("could not open file picker".to_string())?;
+    input.set_type("file");
+    input.set_accept(ACCEPTED_EXTENSIONS);
+
+    let promise = Promise::new(&mut |resolve, _| {
+        let change_input = input.clone();
+        let change_resolve = resolve.clone();
+        let on_change = Closure::once_into_js(move || {
+            let file = change_input
+                .files()
+                .and_then(|files| files.get(0))
+                .map_or(JsValue::NULL, JsValue::from);
+            let _ = change_resolve.call1(&JsValue::NULL, &file);
+        });
+        input.set_onchange(Some(on_change.unchecked_ref()));
+
+        let on_cancel = Closure::once_into_js(move || {
+            let _ = resolve.call1(&JsValue::NULL, &JsValue::NULL);
+        });
+        let _ = input.add_event_listener_with_callback("cancel", on_cancel.unchecked_ref());
+    });
+    input.click();
+
+    let file = JsFuture::from(promise)
+        .await
+        .map_err(|_| "could not read selected file".to_string())?;
+
+    Ok(file.dyn_into::<web_sys::File>().ok())
+}
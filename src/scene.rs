@@ -1,22 +1,38 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
 
 use bytemuck::Pod;
 use crevice::std140::AsStd140;
+use nalgebra::{Rotation3, Unit};
 use wgpu::{
     Device, Queue, TextureFormat, VertexBufferLayout,
     util::{BufferInitDescriptor, DeviceExt},
 };
 
 use crate::{
-    display::{Display, FRAME_FORMAT},
-    load::TSplat,
+    compute::{self, RadixSort, RadixSortPlan},
+    display::{Display, FRAME_FORMAT, texture_size_bytes},
+    load::{OUT_OF_MEMORY, SORT_AXIS, TSplat},
     pbar::{Progress, ProgressBar},
-    utils::{Mat4f, Vec2i, Vec3f},
+    utils::{Mat4f, Vec2i, Vec3f, Vec4f},
 };
 
 const TEXTURE_WIDTH: u32 = 8192;
 const ROWS_PER_CHUNK: u32 = 64;
 
+pub const Z_NEAR: f32 = 0.01;
+pub const Z_FAR: f32 = 100.0;
+// Fraction of triangles kept inside the fit radius, so stray floaters don't dominate it.
+const FIT_QUANTILE: f32 = 0.95;
+// Brightens the flat look of colors built from the DC term alone; tuned on the garden scene.
+pub const DC_ONLY_GAMMA: f32 = 1.25;
+
 fn get_padded_wh(count: usize) -> (u32, u32) {
     let w = TEXTURE_WIDTH;
     let num_chunks = (count as f32 / (w * ROWS_PER_CHUNK) as f32).ceil() as u32;
@@ -104,6 +120,247 @@ where
     texture
 }
 
+// Overwrites a texture made by `upload_texture`, zero-padding `data` to its full size.
+fn write_padded_texture<T: Pod>(queue: &Queue, texture: &wgpu::Texture, data: &[T]) {
+    let (w, h) = (texture.width(), texture.height());
+    let mut padded = data.to_vec();
+    padded.resize((w * h) as usize, T::zeroed());
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&padded),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(w * std::mem::size_of::<T>() as u32),
+            rows_per_image: None,
+        },
+        wgpu::Extent3d {
+            width: w,
+            height: h,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+// Clip volume planes as rows of `vp`, scaled so that `plane.dot(point)` is a signed distance.
+fn frustum_planes(vp: &Mat4f) -> [Vec4f; 6] {
+    let row = |i| vp.row(i).transpose();
+    let w = row(3);
+    [
+        w + row(0),
+        w - row(0),
+        w + row(1),
+        w - row(1),
+        w + row(2),
+        w - row(2),
+    ]
+    .map(|plane| plane / plane.xyz().norm())
+}
+
+fn permute<T: Copy>(values: &[T], order: &[usize]) -> Vec<T> {
+    order.iter().map(|&i| values[i]).collect()
+}
+
+pub fn orbit_basis(up: &Vec3f) -> (Vec3f, Vec3f) {
+    // Headers may declare an up vector along X, which has no cross product with it.
+    let vx = Vec3f::x().cross(up);
+    let vx = if vx.norm() < 0.1 {
+        Vec3f::z().cross(up)
+    } else {
+        vx
+    }
+    .normalize();
+    let vy = up.cross(&vx).normalize();
+    (vx, vy)
+}
+
+pub fn orbit_origin(azimuth: f32, elevation: f32, zoom: f32, center: &Vec3f, up: &Vec3f) -> Vec3f {
+    let (vx, vy) = orbit_basis(up);
+    center
+        + zoom
+            * (elevation.cos() * (azimuth.cos() * vx + azimuth.sin() * vy) + elevation.sin() * up)
+}
+
+// Also returns the eye position, which the splat shader needs alongside the matrix.
+pub fn compute_vp(
+    camera: &Camera,
+    center: &Vec3f,
+    up: &Vec3f,
+    near: f32,
+    far: f32,
+    aspect: f32,
+) -> (Vec3f, Mat4f) {
+    let origin = orbit_origin(camera.azimuth, camera.elevation, camera.zoom, center, up);
+    let view = Mat4f::look_at_rh(&origin.into(), &(*center).into(), up);
+    let proj = Mat4f::new_perspective(aspect, camera.fov, near, far);
+    (origin, proj * view)
+}
+
+fn fit_radius(centroids: &[Vec3f], center: &Vec3f) -> f32 {
+    let mut distances = centroids
+        .iter()
+        .map(|centroid| (centroid - center).norm())
+        .filter(|distance| distance.is_finite())
+        .collect::<Vec<_>>();
+    if distances.is_empty() {
+        return 1.0;
+    }
+    let index = ((distances.len() - 1) as f32 * FIT_QUANTILE) as usize;
+    let (_, radius, _) = distances.select_nth_unstable_by(index, |a, b| a.total_cmp(b));
+    radius.max(Z_NEAR)
+}
+
+// Per-axis quantiles of the centroids, so a few distant floaters don't inflate the box.
+fn fit_bounds(centroids: &[Vec3f]) -> (Vec3f, Vec3f) {
+    let tail = (1.0 - FIT_QUANTILE) / 2.0;
+    let (mut min, mut max) = (Vec3f::zeros(), Vec3f::zeros());
+    for axis in 0..3 {
+        let mut values = centroids
+            .iter()
+            .map(|centroid| centroid[axis])
+            .filter(|value| value.is_finite())
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            continue;
+        }
+        let last = (values.len() - 1) as f32;
+        let low = (last * tail) as usize;
+        let high = (last * (1.0 - tail)) as usize;
+        min[axis] = *values.select_nth_unstable_by(low, f32::total_cmp).1;
+        max[axis] = *values.select_nth_unstable_by(high, f32::total_cmp).1;
+    }
+    (min, max)
+}
+
+fn create_shader_pipeline(
+    device: &Device,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::PipelineLayout,
+    depth_bias: DepthBias,
+) -> wgpu::RenderPipeline {
+    let vertex_buffer_layout = VertexBufferLayout {
+        array_stride: 12,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x3,
+            offset: 0,
+            shader_location: 0,
+        }],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("shader_pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[vertex_buffer_layout],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: FRAME_FORMAT,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: depth_bias.constant,
+                slope_scale: depth_bias.slope_scale,
+                clamp: 0.0,
+            },
+        }),
+        multiview: None,
+        cache: None,
+        multisample: Default::default(),
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct DepthBias {
+    pub constant: i32,
+    pub slope_scale: f32,
+}
+
+impl Default for DepthBias {
+    fn default() -> Self {
+        Self {
+            constant: 0,
+            slope_scale: 0.0,
+        }
+    }
+}
+
+// The file's own up direction, or an override for files that lack metadata.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+    File,
+    PlusY,
+    MinusY,
+    PlusZ,
+    MinusZ,
+}
+
+impl std::fmt::Display for UpAxis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpAxis::File => write!(f, "file"),
+            UpAxis::PlusY => write!(f, "+y"),
+            UpAxis::MinusY => write!(f, "-y"),
+            UpAxis::PlusZ => write!(f, "+z"),
+            UpAxis::MinusZ => write!(f, "-z"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SelectionMode {
+    Highlight,
+    Hide,
+}
+
+impl std::fmt::Display for SelectionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionMode::Highlight => write!(f, "highlight"),
+            SelectionMode::Hide => write!(f, "hide"),
+        }
+    }
+}
+
+pub struct Camera {
+    pub azimuth: f32,
+    pub elevation: f32,
+    pub zoom: f32,
+    pub fov: f32,
+    pub pan_offset: Vec3f,
+    pub translation: Vec3f,
+}
+
+pub struct DrawSettings {
+    pub width: i32,
+    pub height: i32,
+    pub supersample: u32,
+    pub subframe: u32,
+    pub mask: bool,
+    pub fixed_seed: Option<u32>,
+    pub selection: SelectionMode,
+    pub alpha_scale: f32,
+    pub sigma_scale: f32,
+}
+
 #[derive(AsStd140)]
 struct ShaderGlobals {
     fb_size: mint::Vector2<i32>,
@@ -112,6 +369,11 @@ struct ShaderGlobals {
     seed: u32,
     vp: mint::ColumnMatrix4<f32>,
     supersample: u32,
+    mask: u32,
+    selection_mode: u32,
+    gamma: f32,
+    alpha_scale: f32,
+    sigma_scale: f32,
 }
 
 impl Default for ShaderGlobals {
@@ -123,45 +385,488 @@ impl Default for ShaderGlobals {
             seed: Default::default(),
             vp: [0.0; 16].into(),
             supersample: 1,
+            mask: 0,
+            selection_mode: 0,
+            gamma: 1.0,
+            alpha_scale: 1.0,
+            sigma_scale: 1.0,
+        }
+    }
+}
+
+#[derive(AsStd140)]
+struct DepthSortParams {
+    vp: mint::ColumnMatrix4<f32>,
+    frustum_sides: mint::ColumnMatrix4<f32>,
+    frustum_depth: mint::ColumnMatrix4<f32>,
+    count: u32,
+    range_start: u32,
+    range_end: u32,
+}
+
+const DEPTH_SORT_WORKGROUP_SIZE: u32 = 256;
+// Index count, instance count, first index, base vertex and first instance.
+const DRAW_ARGS_RESET: [u32; 5] = [0, 1, 0, 0, 0];
+
+// Back-to-front order for the current view, sorted on the GPU and drawn through an index buffer.
+// Triangles outside the draw range or the view frustum sort last, and the sort counts the rest
+// into the arguments of an indirect draw, so the range keeps selecting in file order and culling
+// happens without a round trip through the CPU.
+struct GpuSort {
+    radix_sort: RadixSort,
+    sort_plan: RadixSortPlan,
+    keys_pipeline: wgpu::ComputePipeline,
+    indices_pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    keys: [wgpu::Buffer; 2],
+    values: [wgpu::Buffer; 2],
+    centroid_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    draw_args: wgpu::Buffer,
+    // The visible index count is copied here for the culled row, one mapping at a time.
+    readback: wgpu::Buffer,
+    readback_busy: Arc<AtomicBool>,
+    readback_copied: Cell<bool>,
+    // usize::MAX until the first count arrives.
+    num_visible: Arc<AtomicUsize>,
+    num_tris: u32,
+    sorted: Cell<bool>,
+}
+
+impl GpuSort {
+    fn new(device: &Device, centroids: &[Vec3f], bounding_radii: &[f32]) -> Option<Self> {
+        let num_tris = centroids.len() as u32;
+        let radix_sort = RadixSort::new(device);
+        let max_workgroups = device.limits().max_compute_workgroups_per_dimension;
+        if num_tris > radix_sort.max_count()
+            || num_tris.div_ceil(DEPTH_SORT_WORKGROUP_SIZE) > max_workgroups
+        {
+            return None;
+        }
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("depth_sort"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                "shaders/depth_sort.wgsl"
+            ))),
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("depth_sort_params"),
+            size: DepthSortParams::std140_size_static() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let centroid_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("depth_sort_centroids"),
+            usage: wgpu::BufferUsages::STORAGE,
+            contents: bytemuck::cast_slice(
+                &centroids
+                    .iter()
+                    .zip(bounding_radii)
+                    .map(|(centroid, &radius)| centroid.push(radius).into())
+                    .collect::<Vec<[f32; 4]>>(),
+            ),
+        });
+        let keys = [(); 2].map(|_| compute::create_storage_buffer(device, num_tris));
+        let values = [(); 2].map(|_| compute::create_storage_buffer(device, num_tris));
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("depth_sort_indices"),
+            size: (num_tris.max(1) * 3 * 4) as u64,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let draw_args = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("depth_sort_draw_args"),
+            usage: wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(&DRAW_ARGS_RESET),
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("depth_sort_readback"),
+            size: 4,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("depth_sort_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, false),
+                storage_entry(3, false),
+                storage_entry(4, false),
+                storage_entry(5, false),
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth_sort_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: centroid_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: keys[0].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: values[0].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: index_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: draw_args.as_entire_binding(),
+                },
+            ],
+        });
+        let sort_plan = radix_sort
+            .plan(
+                device,
+                [&keys[0], &keys[1]],
+                [&values[0], &values[1]],
+                num_tris,
+            )
+            .ok()?;
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("depth_sort_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Some(Self {
+            radix_sort,
+            sort_plan,
+            keys_pipeline: create_pipeline("compute_keys"),
+            indices_pipeline: create_pipeline("write_indices"),
+            bind_group,
+            params_buffer,
+            keys,
+            values,
+            centroid_buffer,
+            index_buffer,
+            draw_args,
+            readback,
+            readback_busy: Arc::new(AtomicBool::new(false)),
+            readback_copied: Cell::new(false),
+            num_visible: Arc::new(AtomicUsize::new(usize::MAX)),
+            num_tris,
+            sorted: Cell::new(false),
+        })
+    }
+
+    fn size_bytes(&self) -> u64 {
+        self.keys
+            .iter()
+            .chain(&self.values)
+            .map(|b| b.size())
+            .sum::<u64>()
+            + self.centroid_buffer.size()
+            + self.index_buffer.size()
+    }
+
+    fn encode(
+        &self,
+        queue: &Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        vp: &Mat4f,
+        (start, end): (usize, usize),
+    ) {
+        let [left, right, bottom, top, near, far] =
+            frustum_planes(vp).map(|plane| plane.transpose());
+        let params = DepthSortParams {
+            vp: (*vp).into(),
+            frustum_sides: Mat4f::from_rows(&[left, right, bottom, top]).into(),
+            frustum_depth: Mat4f::from_rows(&[near, far, near, far]).into(),
+            count: self.num_tris,
+            range_start: start as u32,
+            range_end: end as u32,
+        };
+        queue.write_buffer(&self.params_buffer, 0, params.as_std140().as_bytes());
+        queue.write_buffer(&self.draw_args, 0, bytemuck::cast_slice(&DRAW_ARGS_RESET));
+        let num_workgroups = self.num_tris.div_ceil(DEPTH_SORT_WORKGROUP_SIZE).max(1);
+
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.keys_pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups(num_workgroups, 1, 1);
+        }
+        self.radix_sort.encode(encoder, &self.sort_plan);
+        let mut pass = encoder.begin_compute_pass(&Default::default());
+        pass.set_pipeline(&self.indices_pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.dispatch_workgroups(num_workgroups, 1, 1);
+        drop(pass);
+        if !self.readback_busy.swap(true, Ordering::Relaxed) {
+            encoder.copy_buffer_to_buffer(&self.draw_args, 0, &self.readback, 0, 4);
+            self.readback_copied.set(true);
+        }
+        self.sorted.set(true);
+    }
+
+    // Maps the index count copied by the last sort; the buffer can only be mapped once the copy
+    // has been submitted.
+    fn read_back(&self) {
+        if !self.readback_copied.replace(false) {
+            return;
+        }
+        let readback = self.readback.clone();
+        let busy = self.readback_busy.clone();
+        let num_visible = self.num_visible.clone();
+        self.readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    let index_count =
+                        bytemuck::pod_read_unaligned::<u32>(&readback.slice(..).get_mapped_range());
+                    num_visible.store(index_count as usize / 3, Ordering::Relaxed);
+                    readback.unmap();
+                }
+                busy.store(false, Ordering::Relaxed);
+            });
+    }
+}
+
+#[derive(AsStd140)]
+struct GridGlobals {
+    vp: mint::ColumnMatrix4<f32>,
+    origin: mint::Vector3<f32>,
+    cell: f32,
+    axis_u: mint::Vector3<f32>,
+    extent: f32,
+    axis_v: mint::Vector3<f32>,
+    seed: u32,
+    fb_width: u32,
+}
+
+// Ground grid drawn into the splat pass, so the two share the depth buffer.
+struct Grid {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Grid {
+    fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("grid"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/grid.wgsl"))),
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("grid_uniform_buffer"),
+            size: GridGlobals::std140_size_static() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grid_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grid_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("grid_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: FRAME_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multiview: None,
+            cache: None,
+            multisample: Default::default(),
+        });
+        Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
         }
     }
 }
 
 pub struct Scene {
+    shader: wgpu::ShaderModule,
+    shader_pipeline_layout: wgpu::PipelineLayout,
     shader_pipeline: wgpu::RenderPipeline,
+    depth_bias: DepthBias,
     vertex_buffer: wgpu::Buffer,
+    alpha_sigma_texture: wgpu::Texture,
+    sh_texture: wgpu::Texture,
     texture_bind_group: wgpu::BindGroup,
     uniform_bind_group: wgpu::BindGroup,
     uniform_buffer: wgpu::Buffer,
     num_tris: usize,
+    draw_range: (usize, usize),
+    file_up: Vec3f,
+    up_axis: UpAxis,
+    default_up: Vec3f,
+    up: Vec3f,
+    up_tilt: (f32, f32),
+    center: Vec3f,
+    fit_radius: f32,
+    fit_bounds: (Vec3f, Vec3f),
+    bounds: (Vec3f, Vec3f),
+    bounds_center: Vec3f,
+    centroids: Vec<Vec3f>,
+    bounding_radii: Vec<f32>,
+    cull_index_buffer: wgpu::Buffer,
+    culled_for: Cell<Option<Mat4f>>,
+    num_visible: Cell<usize>,
+    selection: Vec<u8>,
+    num_selected: usize,
+    selection_texture: wgpu::Texture,
+    sort_direction: Vec3f,
+    gpu_sort: Option<GpuSort>,
+    grid: Grid,
+    // Kept on the CPU for the scene data exports and for rebuilding the scene on another GPU,
+    // at about the file's size again.
+    tsplat: TSplat,
+    pub gpu_bytes: u64,
+    pub vertex_bytes: u64,
+    pub alpha_sigma_bytes: u64,
+    pub sh_bytes: u64,
+    pub gamma: f32,
     pub t: u32,
 }
 
 impl Scene {
-    pub async fn new(tsplat: TSplat, display: &Display, pbar: ProgressBar) -> Result<Self, String> {
+    pub async fn new(
+        tsplat: TSplat,
+        display: &Display,
+        atlas_filter: wgpu::FilterMode,
+        pbar: ProgressBar,
+    ) -> Result<Self, String> {
+        Self::upload(tsplat, display, atlas_filter, pbar)
+            .await
+            .map_err(|(err, _)| err)
+    }
+
+    // Like new, but hands the triangles back when the GPU can't hold them.
+    pub async fn upload(
+        tsplat: TSplat,
+        display: &Display,
+        atlas_filter: wgpu::FilterMode,
+        pbar: ProgressBar,
+    ) -> Result<Self, (String, TSplat)> {
         let num_tris = tsplat.points.len();
 
         let TSplat {
             points,
             alpha_sigma,
             sh,
-        } = tsplat;
+            ..
+        } = &tsplat;
 
         let sampler = display.device.create_sampler(&wgpu::SamplerDescriptor {
             label: None,
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
+            mag_filter: atlas_filter,
+            min_filter: atlas_filter,
             ..Default::default()
         });
+        let filterable = atlas_filter == wgpu::FilterMode::Linear;
+        let sampler_binding_type = if filterable {
+            wgpu::SamplerBindingType::Filtering
+        } else {
+            wgpu::SamplerBindingType::NonFiltering
+        };
         pbar.update_status("uploading vertices to gpu".to_string())
             .await;
 
+        display
+            .device
+            .push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+
         let vertex_buffer = display.device.create_buffer_init(&BufferInitDescriptor {
             label: None,
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             contents: bytemuck::cast_slice(points.as_slice()),
         });
 
@@ -169,7 +874,7 @@ impl Scene {
             .await;
 
         let alpha_sigma_texture = upload_texture(
-            alpha_sigma.into_iter(),
+            alpha_sigma.iter().copied(),
             num_tris,
             TextureFormat::Rg16Float,
             &display.device,
@@ -179,8 +884,8 @@ impl Scene {
         .await;
 
         let sh_texture = upload_texture(
-            sh.into_iter(),
-            num_tris,
+            sh.iter().copied(),
+            sh.len(),
             TextureFormat::Rgba16Float,
             &display.device,
             &display.queue,
@@ -188,6 +893,26 @@ impl Scene {
         )
         .await;
 
+        let selection_texture = upload_texture(
+            std::iter::empty::<u8>(),
+            num_tris,
+            TextureFormat::R8Uint,
+            &display.device,
+            &display.queue,
+            |_| std::future::ready(()),
+        )
+        .await;
+
+        if display.device.pop_error_scope().await.is_some() {
+            return Err((OUT_OF_MEMORY.to_string(), tsplat));
+        }
+
+        let vertex_bytes = vertex_buffer.size();
+        let alpha_sigma_bytes = texture_size_bytes(&alpha_sigma_texture);
+        let sh_bytes = texture_size_bytes(&sh_texture);
+        let gpu_bytes =
+            vertex_bytes + alpha_sigma_bytes + sh_bytes + texture_size_bytes(&selection_texture);
+
         pbar.update_status("compiling shaders".to_string()).await;
 
         let shader = display
@@ -199,16 +924,6 @@ impl Scene {
                 ))),
             });
 
-        let vertex_buffer_layout = VertexBufferLayout {
-            array_stride: 12,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[wgpu::VertexAttribute {
-                format: wgpu::VertexFormat::Float32x3,
-                offset: 0,
-                shader_location: 0,
-            }],
-        };
-
         let texture_bind_group_layout =
             display
                 .device
@@ -218,7 +933,7 @@ impl Scene {
                         wgpu::BindGroupLayoutEntry {
                             binding: 0,
                             visibility: wgpu::ShaderStages::VERTEX,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                            ty: wgpu::BindingType::Sampler(sampler_binding_type),
                             count: None,
                         },
                         wgpu::BindGroupLayoutEntry {
@@ -226,7 +941,7 @@ impl Scene {
                             visibility: wgpu::ShaderStages::VERTEX,
                             ty: wgpu::BindingType::Texture {
                                 multisampled: false,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                sample_type: wgpu::TextureSampleType::Float { filterable },
                                 view_dimension: wgpu::TextureViewDimension::D2,
                             },
                             count: None,
@@ -236,7 +951,17 @@ impl Scene {
                             visibility: wgpu::ShaderStages::VERTEX,
                             ty: wgpu::BindingType::Texture {
                                 multisampled: false,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                sample_type: wgpu::TextureSampleType::Float { filterable },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: wgpu::BindingType::Texture {
+                                multisampled: false,
+                                sample_type: wgpu::TextureSampleType::Uint,
                                 view_dimension: wgpu::TextureViewDimension::D2,
                             },
                             count: None,
@@ -266,6 +991,12 @@ impl Scene {
                             &sh_texture.create_view(&wgpu::TextureViewDescriptor::default()),
                         ),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(
+                            &selection_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                        ),
+                    },
                 ],
             });
 
@@ -320,97 +1051,572 @@ impl Scene {
                     bind_group_layouts: &[&texture_bind_group_layout, &uniform_bind_group_layout],
                     push_constant_ranges: &[],
                 });
-        let shader_pipeline =
-            display
-                .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("shader_pipeline"),
-                    layout: Some(&shader_pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &shader,
-                        entry_point: Some("vs_main"),
-                        buffers: &[vertex_buffer_layout],
-                        compilation_options: Default::default(),
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &shader,
-                        entry_point: Some("fs_main"),
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: FRAME_FORMAT,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                        compilation_options: Default::default(),
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        cull_mode: None,
-                        ..Default::default()
-                    },
-                    depth_stencil: Some(wgpu::DepthStencilState {
-                        format: TextureFormat::Depth32Float,
-                        depth_write_enabled: true,
-                        depth_compare: wgpu::CompareFunction::Less,
-                        stencil: wgpu::StencilState::default(),
-                        bias: wgpu::DepthBiasState::default(),
-                    }),
-                    multiview: None,
-                    cache: None,
-                    multisample: Default::default(),
-                });
+        let depth_bias = DepthBias::default();
+        let shader_pipeline = create_shader_pipeline(
+            &display.device,
+            &shader,
+            &shader_pipeline_layout,
+            depth_bias,
+        );
+
+        // Legacy files get the up and center tuned for the garden scene, which is stored Y-down.
+        let (up, center) = match tsplat.format.up {
+            Some(up) => (up, (tsplat.bounds.0 + tsplat.bounds.1) / 2.0),
+            None => {
+                let up = Vec3f::new(-0.0039, -0.8796, -0.4756);
+                let up = if tsplat.format.y_up { -up } else { up };
+                (up, Vec3f::new(0.0549, 0.3402, 0.2599) - up)
+            }
+        };
+        let centroids = points
+            .iter()
+            .map(|tri| (tri[0] + tri[1] + tri[2]) / 3.0)
+            .collect::<Vec<_>>();
+        let fit_radius = fit_radius(&centroids, &center);
+        let fit_bounds = fit_bounds(&centroids);
+        let (w, h) = get_padded_wh(num_tris);
+        let bounding_radii = points
+            .iter()
+            .zip(&centroids)
+            .map(|(tri, centroid)| {
+                tri.iter()
+                    .map(|vertex| (vertex - centroid).norm())
+                    .fold(0.0, f32::max)
+            })
+            .collect::<Vec<_>>();
+        // WebGL has no compute shaders, so it keeps the CPU sort along the view axis.
+        let gpu_sort = compute::supports_compute(&display.adapter)
+            .then(|| GpuSort::new(&display.device, &centroids, &bounding_radii))
+            .flatten();
+        let cull_index_buffer = display.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("cull_indices"),
+            size: (if gpu_sort.is_some() {
+                1
+            } else {
+                num_tris.max(1)
+            } * 3
+                * 4) as u64,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let gpu_bytes =
+            gpu_bytes + gpu_sort.as_ref().map_or(0, GpuSort::size_bytes) + cull_index_buffer.size();
+        let gamma = if tsplat.format.full_sh {
+            1.0
+        } else {
+            DC_ONLY_GAMMA
+        };
 
         Ok(Self {
+            shader,
+            shader_pipeline_layout,
             shader_pipeline,
+            depth_bias,
             vertex_buffer,
+            alpha_sigma_texture,
+            sh_texture,
             texture_bind_group,
             uniform_bind_group,
             uniform_buffer,
             num_tris,
+            draw_range: (0, num_tris),
+            file_up: up,
+            up_axis: UpAxis::File,
+            default_up: up,
+            up,
+            up_tilt: (0.0, 0.0),
+            center,
+            fit_radius,
+            fit_bounds,
+            bounds: tsplat.bounds,
+            bounds_center: (tsplat.bounds.0 + tsplat.bounds.1) / 2.0,
+            centroids,
+            bounding_radii,
+            cull_index_buffer,
+            culled_for: Cell::new(None),
+            num_visible: Cell::new(0),
+            selection: vec![0; (w * h) as usize],
+            num_selected: 0,
+            selection_texture,
+            sort_direction: Vec3f::from(SORT_AXIS),
+            gpu_sort,
+            grid: Grid::new(&display.device),
+            tsplat,
+            gpu_bytes,
+            vertex_bytes,
+            alpha_sigma_bytes,
+            sh_bytes,
+            gamma,
             t: 0,
         })
     }
 
-    pub fn draw(
+    pub fn tsplat(&self) -> &TSplat {
+        &self.tsplat
+    }
+
+    pub fn into_tsplat(self) -> TSplat {
+        self.tsplat
+    }
+
+    pub fn set_depth_bias(&mut self, device: &Device, depth_bias: DepthBias) {
+        if depth_bias != self.depth_bias {
+            self.shader_pipeline = create_shader_pipeline(
+                device,
+                &self.shader,
+                &self.shader_pipeline_layout,
+                depth_bias,
+            );
+            self.depth_bias = depth_bias;
+        }
+    }
+
+    pub fn center(&self) -> Vec3f {
+        self.center
+    }
+
+    pub fn fit_radius(&self) -> f32 {
+        self.fit_radius
+    }
+
+    pub fn fit_bounds(&self) -> (Vec3f, Vec3f) {
+        self.fit_bounds
+    }
+
+    pub fn bounds(&self) -> (Vec3f, Vec3f) {
+        self.bounds
+    }
+
+    pub fn bounds_center(&self) -> Vec3f {
+        self.bounds_center
+    }
+
+    pub fn num_tris(&self) -> usize {
+        self.num_tris
+    }
+
+    pub fn draw_range(&self) -> (usize, usize) {
+        self.draw_range
+    }
+
+    pub fn set_draw_range(&mut self, start: usize, end: usize) {
+        let end = end.min(self.num_tris);
+        self.draw_range = (start.min(end), end);
+        self.culled_for.set(None);
+        if let Some(gpu_sort) = &self.gpu_sort {
+            gpu_sort.sorted.set(false);
+        }
+    }
+
+    pub fn up_tilt(&self) -> (f32, f32) {
+        self.up_tilt
+    }
+
+    pub fn up_axis(&self) -> UpAxis {
+        self.up_axis
+    }
+
+    pub fn set_up_axis(&mut self, up_axis: UpAxis) {
+        self.up_axis = up_axis;
+        self.default_up = match up_axis {
+            UpAxis::File => self.file_up,
+            UpAxis::PlusY => Vec3f::y(),
+            UpAxis::MinusY => -Vec3f::y(),
+            UpAxis::PlusZ => Vec3f::z(),
+            UpAxis::MinusZ => -Vec3f::z(),
+        };
+        let (pitch, roll) = self.up_tilt;
+        self.set_up_tilt(pitch, roll);
+    }
+
+    pub fn set_up_tilt(&mut self, pitch: f32, roll: f32) {
+        let (vx, vy) = orbit_basis(&self.default_up);
+        let pitch_rotation = Rotation3::from_axis_angle(&Unit::new_normalize(vx), pitch);
+        let roll_rotation = Rotation3::from_axis_angle(&Unit::new_normalize(vy), roll);
+        self.up = roll_rotation * pitch_rotation * self.default_up;
+        self.up_tilt = (pitch, roll);
+    }
+
+    // Smallest zoom that keeps the camera outside the fit sphere around the scene center.
+    pub fn min_outside_zoom(&self, camera: &Camera) -> f32 {
+        let direction = orbit_origin(
+            camera.azimuth,
+            camera.elevation,
+            1.0,
+            &Vec3f::zeros(),
+            &self.up,
+        );
+        let offset = self.view_center(camera) - self.center;
+        let along = offset.dot(&direction);
+        let discriminant =
+            along * along - offset.norm_squared() + self.fit_radius * self.fit_radius;
+        if discriminant > 0.0 {
+            -along + discriminant.sqrt()
+        } else {
+            0.0
+        }
+    }
+
+    pub fn num_selected(&self) -> usize {
+        self.num_selected
+    }
+
+    // Selects triangles whose centroids project inside the given NDC rectangle.
+    pub fn select_rect(
+        &mut self,
+        queue: &Queue,
+        camera: &Camera,
+        aspect: f32,
+        min: [f32; 2],
+        max: [f32; 2],
+        additive: bool,
+    ) {
+        let vp = self.view_projection(camera, aspect);
+        for (selected, centroid) in self.selection.iter_mut().zip(&self.centroids) {
+            let clip = vp * centroid.push(1.0);
+            let inside = clip.w > 0.0 && {
+                let (x, y) = (clip.x / clip.w, clip.y / clip.w);
+                x >= min[0] && x <= max[0] && y >= min[1] && y <= max[1]
+            };
+            if inside {
+                *selected = 1;
+            } else if !additive {
+                *selected = 0;
+            }
+        }
+        self.write_selection(queue);
+    }
+
+    pub fn clear_selection(&mut self, queue: &Queue) {
+        self.selection.fill(0);
+        self.write_selection(queue);
+    }
+
+    fn write_selection(&mut self, queue: &Queue) {
+        self.num_selected = self
+            .selection
+            .iter()
+            .filter(|&&selected| selected != 0)
+            .count();
+        write_padded_texture(queue, &self.selection_texture, &self.selection);
+    }
+
+    pub fn sort_direction(&self) -> Vec3f {
+        self.sort_direction
+    }
+
+    pub fn sort_keys(&self, direction: &Vec3f) -> Vec<f32> {
+        self.centroids
+            .iter()
+            .map(|centroid| centroid.dot(direction))
+            .collect()
+    }
+
+    // Reorders every per-triangle array and re-uploads them, e.g. back to front along `direction`.
+    pub fn apply_order(&mut self, queue: &Queue, order: &[usize], direction: Vec3f) {
+        let num_tris = self.num_tris;
+        let tsplat = &mut self.tsplat;
+        tsplat.points = permute(&tsplat.points, order);
+        tsplat.alpha_sigma = permute(&tsplat.alpha_sigma, order);
+        tsplat.sh = tsplat
+            .sh
+            .chunks(num_tris)
+            .flat_map(|block| permute(block, order))
+            .collect();
+        self.centroids = permute(&self.centroids, order);
+        self.bounding_radii = permute(&self.bounding_radii, order);
+        self.culled_for.set(None);
+        let selection = permute(&self.selection[..num_tris], order);
+        self.selection[..num_tris].copy_from_slice(&selection);
+
+        queue.write_buffer(
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(self.tsplat.points.as_slice()),
+        );
+        write_padded_texture(queue, &self.alpha_sigma_texture, &self.tsplat.alpha_sigma);
+        write_padded_texture(queue, &self.sh_texture, &self.tsplat.sh);
+        self.write_selection(queue);
+        self.sort_direction = direction;
+    }
+
+    // The point the camera looks at; panning and flying move it together with the camera.
+    pub fn view_center(&self, camera: &Camera) -> Vec3f {
+        self.center + camera.pan_offset + camera.translation
+    }
+
+    pub fn view_basis(&self, camera: &Camera) -> (Vec3f, Vec3f, Vec3f) {
+        let center = self.view_center(camera);
+        let origin = orbit_origin(
+            camera.azimuth,
+            camera.elevation,
+            camera.zoom,
+            &center,
+            &self.up,
+        );
+        let view = Mat4f::look_at_rh(&origin.into(), &center.into(), &self.up);
+        let right = Vec3f::new(view[(0, 0)], view[(0, 1)], view[(0, 2)]);
+        let up = Vec3f::new(view[(1, 0)], view[(1, 1)], view[(1, 2)]);
+        let forward = -Vec3f::new(view[(2, 0)], view[(2, 1)], view[(2, 2)]);
+        (right, up, forward)
+    }
+
+    fn view_projection(&self, camera: &Camera, aspect: f32) -> Mat4f {
+        let center = self.view_center(camera);
+        compute_vp(camera, &center, &self.up, Z_NEAR, Z_FAR, aspect).1
+    }
+
+    pub fn has_gpu_sort(&self) -> bool {
+        self.gpu_sort.is_some()
+    }
+
+    // Re-sorts back to front when the view changed, or if it was never sorted; must be encoded
+    // before the render pass that draws.
+    pub fn encode_sort(
+        &self,
+        queue: &Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        camera: &Camera,
+        aspect: f32,
+        camera_changed: bool,
+    ) {
+        if let Some(gpu_sort) = &self.gpu_sort
+            && (camera_changed || !gpu_sort.sorted.get())
+        {
+            gpu_sort.encode(
+                queue,
+                encoder,
+                &self.view_projection(camera, aspect),
+                self.draw_range,
+            );
+        }
+    }
+
+    // Rebuilds the list of triangles in the draw range whose bounding spheres touch the view
+    // frustum, once the view projection or range has settled. Spheres keep triangles that
+    // straddle a plane. While the camera moves the whole range is drawn, so the list is built
+    // once per stop rather than uploaded on every frame. The GPU sort culls on its own.
+    pub fn cull(&self, queue: &Queue, camera: &Camera, aspect: f32, camera_changed: bool) {
+        if self.gpu_sort.is_some() {
+            return;
+        }
+        if camera_changed {
+            self.culled_for.set(None);
+            return;
+        }
+        let vp = self.view_projection(camera, aspect);
+        if self.culled_for.get() == Some(vp) {
+            return;
+        }
+        let planes = frustum_planes(&vp);
+        let (start, end) = self.draw_range;
+        let indices = (start..end)
+            .filter(|&tri| {
+                let centroid = self.centroids[tri].push(1.0);
+                let radius = self.bounding_radii[tri];
+                planes.iter().all(|plane| plane.dot(&centroid) >= -radius)
+            })
+            .flat_map(|tri| {
+                let first = tri as u32 * 3;
+                [first, first + 1, first + 2]
+            })
+            .collect::<Vec<u32>>();
+        queue.write_buffer(&self.cull_index_buffer, 0, bytemuck::cast_slice(&indices));
+        self.num_visible.set(indices.len() / 3);
+        self.culled_for.set(Some(vp));
+    }
+
+    // The plane through the lowest corner of the bounds along up, centered below the orbit center.
+    pub fn draw_grid(
         &self,
         queue: &Queue,
         render_pass: &mut wgpu::RenderPass,
-        width: i32,
-        height: i32,
-        supersample: u32,
-        azimuth: f32,
-        elevation: f32,
-        zoom: f32,
+        camera: &Camera,
+        settings: &DrawSettings,
     ) {
-        let up = Vec3f::new(-0.0039, -0.8796, -0.4756);
-        let center = Vec3f::new(0.0549, 0.3402, 0.2599) - up;
-        let vx = Vec3f::new(1.0, 0.0, 0.0).cross(&up).normalize();
-        let vy = up.cross(&vx).normalize();
+        let (min, max) = self.bounds;
+        let floor = (0..8)
+            .map(|corner| {
+                let x = if corner & 1 == 0 { min.x } else { max.x };
+                let y = if corner & 2 == 0 { min.y } else { max.y };
+                let z = if corner & 4 == 0 { min.z } else { max.z };
+                Vec3f::new(x, y, z).dot(&self.up)
+            })
+            .fold(f32::INFINITY, f32::min);
+        let origin = self.center - self.up * (self.center.dot(&self.up) - floor);
+        let axis_u = self.up.cross(&Vec3f::x());
+        let axis_u = if axis_u.norm() < 0.1 {
+            self.up.cross(&Vec3f::z())
+        } else {
+            axis_u
+        }
+        .normalize();
+        let axis_v = self.up.cross(&axis_u);
+        // Minor lines a power of ten below the scene size, so there are 10–100 across its radius.
+        let cell = 10f32.powf(self.fit_radius.log10().floor() - 1.0);
 
-        let r = zoom;
-        let origin = center
-            + r * (elevation.cos() * (azimuth.cos() * vx + azimuth.sin() * vy)
-                + elevation.sin() * up);
+        let aspect = settings.width as f32 / settings.height as f32;
+        let seed = settings.fixed_seed.unwrap_or(self.t);
+        let globals = GridGlobals {
+            vp: self.view_projection(camera, aspect).into(),
+            origin: origin.into(),
+            cell,
+            axis_u: axis_u.into(),
+            extent: 4.0 * self.fit_radius,
+            axis_v: axis_v.into(),
+            seed: seed.wrapping_mul(256).wrapping_add(settings.subframe),
+            fb_width: settings.width as u32,
+        };
+        queue.write_buffer(&self.grid.uniform_buffer, 0, globals.as_std140().as_bytes());
+        render_pass.set_pipeline(&self.grid.pipeline);
+        render_pass.set_bind_group(0, &self.grid.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+
+    // The count from the last completed cull, which lags the view by a frame or more.
+    pub fn num_culled(&self) -> Option<usize> {
+        let (start, end) = self.draw_range;
+        let num_visible = match &self.gpu_sort {
+            Some(gpu_sort) => match gpu_sort.num_visible.load(Ordering::Relaxed) {
+                usize::MAX => return None,
+                num_visible => num_visible,
+            },
+            None => self.num_visible.get(),
+        };
+        Some((end - start).saturating_sub(num_visible))
+    }
+
+    // Must be called once the commands that sorted have been submitted.
+    pub fn read_back(&self) {
+        if let Some(gpu_sort) = &self.gpu_sort {
+            gpu_sort.read_back();
+        }
+    }
 
-        let view = Mat4f::look_at_rh(&origin.into(), &center.into(), &up);
+    pub fn draw(
+        &self,
+        queue: &Queue,
+        render_pass: &mut wgpu::RenderPass,
+        camera: &Camera,
+        settings: &DrawSettings,
+    ) {
+        let center = self.view_center(camera);
+        let aspect = settings.width as f32 / settings.height as f32;
+        let (origin, vp) = compute_vp(camera, &center, &self.up, Z_NEAR, Z_FAR, aspect);
 
-        let aspect = width as f32 / height as f32;
-        let proj = Mat4f::new_perspective(aspect, 0.85, 0.01, 100.0);
-        let vp = proj * view;
+        let seed = settings.fixed_seed.unwrap_or(self.t);
 
         let globals = ShaderGlobals {
-            fb_size: Vec2i::new(width, height).into(),
+            fb_size: Vec2i::new(settings.width, settings.height).into(),
             origin: origin.into(),
             num_tris: self.num_tris as u32,
-            seed: self.t,
+            seed,
             vp: vp.into(),
-            supersample,
-            ..ShaderGlobals::default()
+            supersample: settings.supersample,
+            mask: settings.mask as u32,
+            selection_mode: match settings.selection {
+                SelectionMode::Highlight => 1,
+                SelectionMode::Hide => 2,
+            },
+            gamma: self.gamma,
+            alpha_scale: settings.alpha_scale,
+            sigma_scale: settings.sigma_scale,
         };
         queue.write_buffer(&self.uniform_buffer, 0, globals.as_std140().as_bytes());
         render_pass.set_pipeline(&self.shader_pipeline);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
         render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
-        render_pass.draw(0..(self.num_tris * 3) as u32, 0..1);
+        match &self.gpu_sort {
+            Some(gpu_sort) => {
+                render_pass
+                    .set_index_buffer(gpu_sort.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed_indirect(&gpu_sort.draw_args, 0);
+            }
+            None if self.culled_for.get().is_some() => {
+                render_pass
+                    .set_index_buffer(self.cull_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..(self.num_visible.get() * 3) as u32, 0, 0..1);
+            }
+            None => {
+                let (start, end) = self.draw_range;
+                render_pass.draw((start * 3) as u32..(end * 3) as u32, 0..1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn camera(zoom: f32) -> Camera {
+        Camera {
+            azimuth: 0.7,
+            elevation: 0.3,
+            zoom,
+            fov: 60f32.to_radians(),
+            pan_offset: Vec3f::zeros(),
+            translation: Vec3f::zeros(),
+        }
+    }
+
+    fn project(vp: &Mat4f, point: &Vec3f) -> Vec3f {
+        let clip = vp * point.push(1.0);
+        clip.xyz() / clip.w
+    }
+
+    #[wasm_bindgen_test]
+    fn orbit_center_projects_to_ndc_origin() {
+        let center = Vec3f::new(1.0, -2.0, 0.5);
+        let up = Vec3f::new(-0.0039, -0.8796, -0.4756).normalize();
+        let (_, vp) = compute_vp(&camera(3.0), &center, &up, Z_NEAR, Z_FAR, 1.5);
+        let ndc = project(&vp, &center);
+        assert!(ndc.x.abs() < 1e-5 && ndc.y.abs() < 1e-5, "{ndc:?}");
+        assert!(ndc.z > -1.0 && ndc.z < 1.0, "{ndc:?}");
+    }
+
+    #[wasm_bindgen_test]
+    fn zoom_is_the_distance_to_the_center() {
+        let center = Vec3f::new(1.0, -2.0, 0.5);
+        for zoom in [0.5, 2.0, 8.0] {
+            let (origin, _) = compute_vp(&camera(zoom), &center, &Vec3f::y(), Z_NEAR, Z_FAR, 1.0);
+            assert!(((origin - center).norm() - zoom).abs() < 1e-4);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn up_projects_upwards() {
+        let (_, vp) = compute_vp(
+            &camera(3.0),
+            &Vec3f::zeros(),
+            &Vec3f::z(),
+            Z_NEAR,
+            Z_FAR,
+            1.0,
+        );
+        let ndc = project(&vp, &(0.1 * Vec3f::z()));
+        assert!(ndc.y > 0.0 && ndc.x.abs() < 1e-5, "{ndc:?}");
+    }
+
+    #[wasm_bindgen_test]
+    fn orbit_basis_handles_up_along_x() {
+        let (vx, vy) = orbit_basis(&Vec3f::x());
+        assert!(vx.iter().chain(vy.iter()).all(|c| c.is_finite()));
+        assert!(vx.dot(&vy).abs() < 1e-6 && vx.dot(&Vec3f::x()).abs() < 1e-6);
+    }
+
+    #[wasm_bindgen_test]
+    fn fit_bounds_ignores_floaters() {
+        let mut centroids = (0..100)
+            .map(|i| Vec3f::new(i as f32 / 99.0, 0.0, -(i as f32) / 99.0))
+            .collect::<Vec<_>>();
+        centroids.push(Vec3f::new(1000.0, f32::NAN, -1000.0));
+        let (min, max) = fit_bounds(&centroids);
+        assert!(max.x < 1.0 && min.z > -1.0, "{min:?} {max:?}");
+        assert!(min.x < 0.05 && max.z > -0.05, "{min:?} {max:?}");
+        assert_eq!((min.y, max.y), (0.0, 0.0));
     }
 }
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use crate::display::RenderResolution;
+
+const STORAGE_KEY: &str = "tsplat-settings";
+const SETTINGS_VERSION: u32 = 1;
+
+// Fields missing from an older blob take their defaults, so new fields only need a default.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub render_resolution: RenderResolution,
+    pub supersample: u32,
+    pub panel_open: bool,
+    pub background: [f32; 3],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            render_resolution: RenderResolution::Ws720P,
+            supersample: 3,
+            panel_open: true,
+            background: crate::DEFAULT_BACKGROUND,
+        }
+    }
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+// Blobs written by a newer viewer are ignored rather than half-understood.
+pub fn load() -> Settings {
+    local_storage()
+        .and_then(|storage| storage.get_item(STORAGE_KEY).ok()?)
+        .and_then(|json| serde_json::from_str::<Settings>(&json).ok())
+        .filter(|settings| settings.version <= SETTINGS_VERSION)
+        .map(|settings| Settings {
+            version: SETTINGS_VERSION,
+            ..settings
+        })
+        .unwrap_or_default()
+}
+
+pub fn save(settings: &Settings) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(settings)) {
+        let _ = storage.set_item(STORAGE_KEY, &json);
+    }
+}
@@ -34,8 +34,97 @@ var tex_sampler: sampler;
 @binding(1)
 var tex: texture_2d<f32>;
 
+struct Globals {
+    exposure: f32,
+    srgb_encode: u32,
+    upscale_filter: u32,
+    dither_seed: u32,
+    tonemap: u32,
+}
+
+@group(1)
+@binding(0)
+var<uniform> globals: Globals;
+
+fn load_texel(p: vec2<i32>) -> vec3<f32> {
+    let dimensions = vec2<i32>(textureDimensions(tex));
+    return textureLoad(tex, clamp(p, vec2<i32>(0), dimensions - 1), 0).xyz;
+}
+
+fn sample_bilinear(uv: vec2<f32>) -> vec3<f32> {
+    let p = uv * vec2<f32>(textureDimensions(tex)) - 0.5;
+    let p0 = floor(p);
+    let f = p - p0;
+    let i = vec2<i32>(p0);
+    let top = mix(load_texel(i), load_texel(i + vec2<i32>(1, 0)), f.x);
+    let bottom = mix(load_texel(i + vec2<i32>(0, 1)), load_texel(i + vec2<i32>(1, 1)), f.x);
+    return mix(top, bottom, f.y);
+}
+
+fn sample_sharpened(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(tex));
+    let center = sample_bilinear(uv);
+    let blur = (sample_bilinear(uv + vec2<f32>(texel.x, 0.0))
+        + sample_bilinear(uv - vec2<f32>(texel.x, 0.0))
+        + sample_bilinear(uv + vec2<f32>(0.0, texel.y))
+        + sample_bilinear(uv - vec2<f32>(0.0, texel.y))) * 0.25;
+    return max(center + 0.5 * (center - blur), vec3<f32>(0.0));
+}
+
+fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let c = clamp(linear, vec3<f32>(0.0), vec3<f32>(1.0));
+    let lo = c * 12.92;
+    let hi = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(hi, lo, c <= vec3<f32>(0.0031308));
+}
+
+// 0 leaves values to be clamped, 1 is Reinhard, 2 is Narkowicz's fit of the ACES curve.
+fn tonemap(rgb: vec3<f32>, mode: u32) -> vec3<f32> {
+    if (mode == 1u) {
+        return rgb / (1.0 + rgb);
+    } else if (mode == 2u) {
+        return clamp(
+            (rgb * (2.51 * rgb + 0.03)) / (rgb * (2.43 * rgb + 0.59) + 0.14),
+            vec3<f32>(0.0),
+            vec3<f32>(1.0),
+        );
+    }
+    return rgb;
+}
+
+fn hash(seed: u32) -> u32 {
+    var x = seed;
+    x ^= x >> 17u;
+    x *= 0xed5ad4bbu;
+    x ^= x >> 11u;
+    x *= 0xac4c1b51u;
+    x ^= x >> 15u;
+    x *= 0x31848babu;
+    x ^= x >> 14u;
+    return x;
+}
+
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-    let rgb = textureSample(tex, tex_sampler, vertex.uv).xyz;
+    var rgb: vec3<f32>;
+    if (globals.upscale_filter == 1u) {
+        rgb = sample_bilinear(vertex.uv);
+    } else if (globals.upscale_filter == 2u) {
+        rgb = sample_sharpened(vertex.uv);
+    } else {
+        rgb = textureSample(tex, tex_sampler, vertex.uv).xyz;
+    }
+    rgb = tonemap(rgb * globals.exposure, globals.tonemap);
+    // The only encode between the linear accumulation and the non-sRGB surface;
+    // export::display_rgba8 mirrors it for screenshots.
+    if (globals.srgb_encode != 0u) {
+        rgb = linear_to_srgb(rgb);
+    }
+    // Sub-quantum noise at the one 8-bit quantization, reseeded every frame.
+    if (globals.dither_seed != 0u) {
+        let p = vec2<u32>(vertex.position.xy);
+        let noise = f32(hash((p.x | (p.y << 16u)) ^ hash(globals.dither_seed))) / 4294967295.0;
+        rgb += vec3<f32>((noise - 0.5) / 255.0);
+    }
     return vec4<f32>(rgb, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,90 @@
+// Per-triangle depth keys for the radix sort, and the index buffer built from its result.
+
+struct Params {
+    vp: mat4x4<f32>,
+    // Rows are the left, right, bottom and top frustum planes, then the near and far planes twice.
+    frustum_sides: mat4x4<f32>,
+    frustum_depth: mat4x4<f32>,
+    count: u32,
+    range_start: u32,
+    range_end: u32,
+}
+
+// Laid out as the arguments of an indexed indirect draw.
+struct DrawArgs {
+    index_count: atomic<u32>,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+@group(0)
+@binding(0)
+var<uniform> params: Params;
+
+// Centroids with the bounding radius in w.
+@group(0)
+@binding(1)
+var<storage, read> centroids: array<vec4<f32>>;
+
+@group(0)
+@binding(2)
+var<storage, read_write> keys: array<u32>;
+
+@group(0)
+@binding(3)
+var<storage, read_write> values: array<u32>;
+
+@group(0)
+@binding(4)
+var<storage, read_write> indices: array<u32>;
+
+@group(0)
+@binding(5)
+var<storage, read_write> draw_args: DrawArgs;
+
+// Triangles outside the draw range or the frustum take this key, which sorts after every drawn one.
+const EXCLUDED_KEY: u32 = 0xffffffffu;
+
+// Maps floats to u32s with the same ordering, inverted so that the farthest sorts first.
+fn far_first_key(depth: f32) -> u32 {
+    let bits = bitcast<u32>(depth);
+    let ordered = select(bits ^ 0x80000000u, ~bits, (bits & 0x80000000u) != 0u);
+    return ~ordered;
+}
+
+@compute
+@workgroup_size(256)
+fn compute_keys(@builtin(global_invocation_id) id: vec3<u32>) {
+    let tri = id.x;
+    if (tri >= params.count) {
+        return;
+    }
+    let centroid = vec4<f32>(centroids[tri].xyz, 1.0);
+    let min_distance = vec4<f32>(-centroids[tri].w);
+    let visible = tri >= params.range_start && tri < params.range_end
+        && all(params.frustum_sides * centroid >= min_distance)
+        && all(params.frustum_depth * centroid >= min_distance);
+    if (visible) {
+        let clip = params.vp * centroid;
+        keys[tri] = min(far_first_key(clip.w), EXCLUDED_KEY - 1u);
+        atomicAdd(&draw_args.index_count, 3u);
+    } else {
+        keys[tri] = EXCLUDED_KEY;
+    }
+    values[tri] = tri;
+}
+
+@compute
+@workgroup_size(256)
+fn write_indices(@builtin(global_invocation_id) id: vec3<u32>) {
+    let position = id.x;
+    if (position >= params.count) {
+        return;
+    }
+    let tri = values[position];
+    for (var k = 0u; k < 3u; k++) {
+        indices[position * 3u + k] = tri * 3u + k;
+    }
+}
@@ -0,0 +1,41 @@
+// Debug view of the splat depth buffer: near is white, log-spaced out to black at the far plane.
+
+struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var result: VertexOutput;
+    let corner = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    result.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    result.position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    return result;
+}
+
+struct Planes {
+    near: f32,
+    far: f32,
+}
+
+@group(0)
+@binding(0)
+var depth_tex: texture_depth_2d;
+
+@group(0)
+@binding(1)
+var<uniform> planes: Planes;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let dimensions = vec2<f32>(textureDimensions(depth_tex));
+    let p = vec2<i32>(min(vertex.uv * dimensions, dimensions - 1.0));
+    let depth = textureLoad(depth_tex, p, 0);
+    // Inverts the OpenGL-style projection from scene.rs, whose NDC z lands in the depth buffer.
+    let n = planes.near;
+    let f = planes.far;
+    let distance = 2.0 * f * n / (depth * (n - f) + f + n);
+    let t = clamp(log(distance / n) / log(f / n), 0.0, 1.0);
+    return vec4<f32>(vec3<f32>(1.0 - t), 1.0);
+}
@@ -37,23 +37,52 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
     return result;
 }
 
-@fragment
-fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-    let prev_rgbc = textureLoad(prev_frame, vec2<i32>(i32(vertex.position.x), i32(vertex.position.y)), 0);
-    let dimensions = textureDimensions(tex);
+// Caps the history weight while the camera moves so the image follows the motion.
+const DENOISE_MAX_HISTORY: f32 = 8.0;
+
+fn average_samples(pixel: vec2<i32>, dimensions: vec2<u32>, supersample: u32) -> vec3<f32> {
     var rgb = vec3<f32>(0.0, 0.0, 0.0);
-    let supersample = globals.x;
     for (var i: u32 = 0; i < supersample; i++) {
         for (var j: u32 = 0; j < supersample; j++) {
-            let u = (f32(supersample) * vertex.position.x + f32(i)) / f32(dimensions.x);
-            let v = (f32(supersample) * vertex.position.y + f32(j)) / f32(dimensions.y);
+            let u = (f32(supersample) * (f32(pixel.x) + 0.5) + f32(i)) / f32(dimensions.x);
+            let v = (f32(supersample) * (f32(pixel.y) + 0.5) + f32(j)) / f32(dimensions.y);
             let uv = vec2<f32>(u, v);
             rgb += textureSample(tex, tex_sampler, uv).xyz;
         }
     }
-    rgb /= f32(supersample * supersample);
+    return rgb / f32(supersample * supersample);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(i32(vertex.position.x), i32(vertex.position.y));
+    let prev_rgbc = textureLoad(prev_frame, pixel, 0);
+    let dimensions = textureDimensions(tex);
+    let supersample = globals.x;
+    let rgb = average_samples(pixel, dimensions, supersample);
     let stale_camera = globals.y;
-    if (stale_camera == 1u) {
+    let denoise = globals.z;
+    if (stale_camera == 1u && denoise == 1u) {
+        // Clamp history to the mean ± k·σ of the current 3×3 neighbourhood to reject ghosts.
+        var sum = vec3<f32>(0.0, 0.0, 0.0);
+        var sum_sq = vec3<f32>(0.0, 0.0, 0.0);
+        let max_pixel = vec2<i32>(i32(dimensions.x / supersample) - 1, i32(dimensions.y / supersample) - 1);
+        for (var dx: i32 = -1; dx <= 1; dx++) {
+            for (var dy: i32 = -1; dy <= 1; dy++) {
+                let neighbour = clamp(pixel + vec2<i32>(dx, dy), vec2<i32>(0, 0), max_pixel);
+                let sample = average_samples(neighbour, dimensions, supersample);
+                sum += sample;
+                sum_sq += sample * sample;
+            }
+        }
+        let mean = sum / 9.0;
+        let sigma = sqrt(max(sum_sq / 9.0 - mean * mean, vec3<f32>(0.0, 0.0, 0.0)));
+        let strength = bitcast<f32>(globals.w);
+        let prev_rgb = clamp(prev_rgbc.xyz, mean - strength * sigma, mean + strength * sigma);
+        let prev_count = min(prev_rgbc.w, DENOISE_MAX_HISTORY);
+        let new_rgb = (prev_rgb * prev_count + rgb) / (prev_count + 1.0);
+        return vec4<f32>(new_rgb, prev_count + 1.0);
+    } else if (stale_camera == 1u) {
         return vec4<f32>(rgb, 1.0);
     } else {
         let prev_rgb = prev_rgbc.xyz;
@@ -65,4 +94,4 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
         let new_count = prev_count + 1.0;
         return vec4<f32>(new_rgb, new_count);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,73 @@
+// Reference grid on the ground plane below the scene, covered stochastically like the splats so it
+// accumulates the same way and occludes through the shared depth buffer.
+
+struct Globals {
+    vp: mat4x4<f32>,
+    origin: vec3<f32>,
+    cell: f32,
+    axis_u: vec3<f32>,
+    extent: f32,
+    axis_v: vec3<f32>,
+    seed: u32,
+    fb_width: u32,
+}
+
+@group(0)
+@binding(0)
+var<uniform> globals: Globals;
+
+struct VertexOutput {
+    @location(0) plane: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let plane = corners[index] * globals.extent;
+    let world = globals.origin + globals.axis_u * plane.x + globals.axis_v * plane.y;
+
+    var result: VertexOutput;
+    result.plane = plane;
+    result.position = globals.vp * vec4<f32>(world, 1.0);
+    return result;
+}
+
+fn hash(seed: u32) -> u32 {
+    var x = seed;
+    x ^= x >> 17u;
+    x *= 0xed5ad4bbu;
+    x ^= x >> 11u;
+    x *= 0xac4c1b51u;
+    x ^= x >> 15u;
+    x *= 0x31848babu;
+    x ^= x >> 14u;
+    return x;
+}
+
+// Antialiased coverage of lines at integer coordinates, about a pixel wide.
+fn lines(p: vec2<f32>) -> f32 {
+    let d = abs(fract(p - 0.5) - 0.5) / fwidth(p);
+    return 1.0 - min(min(d.x, d.y), 1.0);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let p = vertex.plane / globals.cell;
+    let coverage = max(0.35 * lines(p), lines(p / 10.0));
+    let fade = 1.0 - smoothstep(0.3, 1.0, length(vertex.plane) / globals.extent);
+
+    let seed = u32(vertex.position.x) + u32(vertex.position.y) * globals.fb_width;
+    let u = f32(hash(seed ^ hash(globals.seed))) / 4294967295.0;
+    if (coverage * fade * 0.6 < u) {
+        discard;
+    }
+    return vec4<f32>(0.5, 0.5, 0.5, 1.0);
+}
@@ -0,0 +1,37 @@
+// Shown in the letterboxed frame until a scene loads: a faint ring with a bright arc turning slowly.
+
+struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var result: VertexOutput;
+    let corner = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    result.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    result.position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    return result;
+}
+
+struct Globals {
+    background: vec3<f32>,
+    time: f32,
+    aspect: f32,
+}
+
+@group(0)
+@binding(0)
+var<uniform> globals: Globals;
+
+const PI: f32 = 3.14159265;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let p = (vertex.uv - 0.5) * vec2<f32>(globals.aspect, 1.0);
+    let ring = 1.0 - smoothstep(0.004, 0.012, abs(length(p) - 0.06));
+    let angle = fract((atan2(p.y, p.x) + globals.time * 2.0) / (2.0 * PI));
+    let arc = 0.15 + 0.85 * angle * angle;
+    let ink = select(vec3<f32>(1.0), vec3<f32>(0.0), dot(globals.background, vec3<f32>(1.0 / 3.0)) > 0.5);
+    return vec4<f32>(mix(globals.background, ink, 0.35 * ring * arc), 1.0);
+}
@@ -0,0 +1,96 @@
+// Work-efficient (Blelloch) exclusive scan, 512 elements per workgroup.
+
+struct Params {
+    count: u32,
+}
+
+@group(0)
+@binding(0)
+var<uniform> params: Params;
+
+@group(0)
+@binding(1)
+var<storage, read> input: array<u32>;
+
+@group(0)
+@binding(2)
+var<storage, read_write> output: array<u32>;
+
+@group(0)
+@binding(3)
+var<storage, read_write> block_sums: array<u32>;
+
+var<workgroup> temp: array<u32, 512>;
+
+@compute
+@workgroup_size(256)
+fn scan_blocks(
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let base = workgroup_id.x * 512u;
+    let a_index = local_id.x;
+    let b_index = local_id.x + 256u;
+
+    temp[a_index] = 0u;
+    temp[b_index] = 0u;
+    if (base + a_index < params.count) {
+        temp[a_index] = input[base + a_index];
+    }
+    if (base + b_index < params.count) {
+        temp[b_index] = input[base + b_index];
+    }
+
+    var offset = 1u;
+    for (var d = 256u; d > 0u; d >>= 1u) {
+        workgroupBarrier();
+        if (local_id.x < d) {
+            let a = offset * (2u * local_id.x + 1u) - 1u;
+            let b = offset * (2u * local_id.x + 2u) - 1u;
+            temp[b] += temp[a];
+        }
+        offset <<= 1u;
+    }
+
+    workgroupBarrier();
+    if (local_id.x == 0u) {
+        block_sums[workgroup_id.x] = temp[511];
+        temp[511] = 0u;
+    }
+
+    for (var d = 1u; d < 512u; d <<= 1u) {
+        offset >>= 1u;
+        workgroupBarrier();
+        if (local_id.x < d) {
+            let a = offset * (2u * local_id.x + 1u) - 1u;
+            let b = offset * (2u * local_id.x + 2u) - 1u;
+            let t = temp[a];
+            temp[a] = temp[b];
+            temp[b] += t;
+        }
+    }
+
+    workgroupBarrier();
+    if (base + a_index < params.count) {
+        output[base + a_index] = temp[a_index];
+    }
+    if (base + b_index < params.count) {
+        output[base + b_index] = temp[b_index];
+    }
+}
+
+@compute
+@workgroup_size(256)
+fn add_block_offsets(
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let base = workgroup_id.x * 512u;
+    let offset = block_sums[workgroup_id.x];
+    for (var i = 0u; i < 2u; i++) {
+        let index = base + local_id.x + i * 256u;
+        if (index < params.count) {
+            output[index] += offset;
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Stable LSD radix sort of (key, value) pairs, 4 bits per pass and 256 items per workgroup.
+
+struct Params {
+    count: u32,
+    shift: u32,
+    num_blocks: u32,
+}
+
+@group(0)
+@binding(0)
+var<uniform> params: Params;
+
+@group(0)
+@binding(1)
+var<storage, read> keys_in: array<u32>;
+
+@group(0)
+@binding(2)
+var<storage, read> values_in: array<u32>;
+
+@group(0)
+@binding(3)
+var<storage, read_write> keys_out: array<u32>;
+
+@group(0)
+@binding(4)
+var<storage, read_write> values_out: array<u32>;
+
+// Digit-major counts per block when counting, and their exclusive prefix sum when scattering.
+@group(0)
+@binding(5)
+var<storage, read_write> block_digits: array<u32>;
+
+var<workgroup> histogram: array<atomic<u32>, 16>;
+var<workgroup> digits: array<u32, 256>;
+
+// Out-of-range items get digit 16, which matches nothing.
+fn digit_of(index: u32) -> u32 {
+    if (index >= params.count) {
+        return 16u;
+    }
+    return (keys_in[index] >> params.shift) & 15u;
+}
+
+@compute
+@workgroup_size(256)
+fn count_digits(
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    if (local_id.x < 16u) {
+        atomicStore(&histogram[local_id.x], 0u);
+    }
+    workgroupBarrier();
+
+    let digit = digit_of(workgroup_id.x * 256u + local_id.x);
+    if (digit < 16u) {
+        atomicAdd(&histogram[digit], 1u);
+    }
+    workgroupBarrier();
+
+    if (local_id.x < 16u) {
+        block_digits[local_id.x * params.num_blocks + workgroup_id.x] =
+            atomicLoad(&histogram[local_id.x]);
+    }
+}
+
+@compute
+@workgroup_size(256)
+fn scatter(
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let index = workgroup_id.x * 256u + local_id.x;
+    let digit = digit_of(index);
+    digits[local_id.x] = digit;
+    workgroupBarrier();
+
+    if (digit == 16u) {
+        return;
+    }
+    // Counting earlier items with the same digit keeps the sort stable.
+    var rank = 0u;
+    for (var i = 0u; i < local_id.x; i++) {
+        if (digits[i] == digit) {
+            rank++;
+        }
+    }
+    let destination = block_digits[digit * params.num_blocks + workgroup_id.x] + rank;
+    keys_out[destination] = keys_in[index];
+    values_out[destination] = values_in[index];
+}
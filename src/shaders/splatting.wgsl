@@ -10,6 +10,10 @@ var alpha_sigma_texture: texture_2d<f32>;
 @binding(2)
 var sh_texture: texture_2d<f32>;
 
+@group(0)
+@binding(3)
+var selection_texture: texture_2d<u32>;
+
 struct Globals {
     fb_size: vec2<i32>,
     origin: vec3<f32>,
@@ -17,6 +21,11 @@ struct Globals {
     seed: u32,
     vp: mat4x4<f32>,
     supersample: u32,
+    mask: u32,
+    selection_mode: u32,
+    gamma: f32,
+    alpha_scale: f32,
+    sigma_scale: f32,
 }
 
 @group(1)
@@ -164,15 +173,26 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
     let triangle_index = vertex.index / 3u;
     let tex_coord = idx2vec2(triangle_index);
     let alpha_sigma = textureLoad(alpha_sigma_texture, tex_coord, 0).xy;
-    let alpha = alpha_sigma.x;
-    let sigma = alpha_sigma.y;
+    let alpha = alpha_sigma.x * globals.alpha_scale;
+    // pow(phi, sigma) in the fragment shader is undefined for sigma <= 0, which would write NaNs
+    // into the accumulation, and huge values just erase the triangle.
+    let sigma = clamp(alpha_sigma.y * globals.sigma_scale, 0.01, 100.0);
 
     var v = vertex.position - globals.origin;
     v /= length(v);
 
-    let rgb = sh2rgb(v, triangle_index);
-
     var result: VertexOutput;
+    let selected = textureLoad(selection_texture, tex_coord, 0).x != 0u;
+    if (selected && globals.selection_mode == 2u) {
+        // A zero position is clipped away, hiding the triangle.
+        return result;
+    }
+
+    var rgb = pow(max(sh2rgb(v, triangle_index), vec3<f32>(0.0)), vec3<f32>(1.0 / globals.gamma));
+    if (selected && globals.selection_mode == 1u) {
+        rgb = mix(rgb, vec3<f32>(1.0, 0.5, 0.0), 0.5);
+    }
+
     result.rgba = vec4<f32>(rgb, alpha);
     if (vertex.index % 3u == 0u) {
         result.uvws = vec4<f32>(3.0, 0.0, 0.0, sigma);
@@ -199,6 +219,11 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
         discard;
     }
 
+    // Opaque white over the black clear, so the accumulated value is coverage.
+    if (globals.mask != 0u) {
+        return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    }
+
     let rgba = vec4<f32>(vertex.rgba.xyz, 1.0);
 
     return rgba;
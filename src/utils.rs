@@ -1,4 +1,5 @@
 use half::f16;
+use wasm_bindgen::JsCast;
 
 pub type Vec2f = nalgebra::Vector2<f32>;
 pub type Vec3f = nalgebra::Vector3<f32>;
@@ -18,12 +19,44 @@ pub type Vec4u = nalgebra::Vector4<u32>;
 
 pub type Mat4f = nalgebra::Matrix4<f32>;
 
+// Also runs in the scene parser worker, which has no window.
 pub async fn yield_async(timeout: i32) {
     let promise = js_sys::Promise::new(&mut |resolve, _| {
-        web_sys::window()
-            .unwrap()
-            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, timeout)
-            .unwrap();
+        let global = js_sys::global();
+        match global.dyn_ref::<web_sys::Window>() {
+            Some(window) => window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, timeout)
+                .unwrap(),
+            None => global
+                .unchecked_ref::<web_sys::WorkerGlobalScope>()
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, timeout)
+                .unwrap(),
+        };
     });
     wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
 }
+
+pub fn dispatch_canvas_event(
+    canvas: &web_sys::HtmlCanvasElement,
+    event_type: &str,
+    detail: &wasm_bindgen::JsValue,
+) {
+    let init = web_sys::CustomEventInit::new();
+    init.set_bubbles(true);
+    init.set_detail(detail);
+    if let Ok(event) = web_sys::CustomEvent::new_with_event_init_dict(event_type, &init) {
+        let _ = canvas.dispatch_event(&event);
+    }
+}
+
+pub fn query_param(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get(name)
+}
+
+pub fn hash_params() -> Option<web_sys::UrlSearchParams> {
+    let hash = web_sys::window()?.location().hash().ok()?;
+    web_sys::UrlSearchParams::new_with_str(hash.trim_start_matches('#')).ok()
+}
@@ -0,0 +1,347 @@
+use std::cell::{Cell, RefCell};
+
+use futures::{AsyncRead, AsyncReadExt, StreamExt, TryStreamExt, channel::mpsc, future::Either};
+use js_sys::{Array, Float32Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{DedicatedWorkerGlobalScope, MessageEvent, Worker};
+
+use crate::{
+    load::{self, Format, TSplat},
+    pbar::{Progress, ProgressBar, make_progress_bar},
+    utils::{Vec3f, yield_async},
+};
+
+const CHUNK_BYTES: usize = 1 << 20;
+const PROGRESS_INTERVAL_MS: i32 = 50;
+
+// The snippet lands in pkg/snippets/<crate>-<hash>/, two levels below the generated bindings.
+#[wasm_bindgen(inline_js = "export function bindings_url() { \
+    return new URL('../../webviewer.js', import.meta.url).href; }")]
+extern "C" {
+    fn bindings_url() -> String;
+}
+
+fn message(kind: &str) -> Object {
+    let msg = Object::new();
+    set(&msg, "type", kind);
+    msg
+}
+
+fn set(target: &Object, key: &str, value: impl Into<JsValue>) {
+    let _ = Reflect::set(target, &key.into(), &value.into());
+}
+
+fn get(source: &JsValue, key: &str) -> JsValue {
+    Reflect::get(source, &key.into()).unwrap_or(JsValue::UNDEFINED)
+}
+
+fn post(msg: &Object, transfer: Option<&Array>) {
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let _ = match transfer {
+        Some(transfer) => scope.post_message_with_transfer(msg, transfer),
+        None => scope.post_message(msg),
+    };
+}
+
+// The triangle buffers travel as raw bytes in transferred ArrayBuffers, so they are copied
+// once into JS memory on each side rather than structured-cloned.
+fn encode_tsplat(kind: &str, tsplat: &TSplat) -> (Object, Array) {
+    let msg = message(kind);
+    let transfer = Array::new();
+    for (key, bytes) in [
+        ("points", bytemuck::cast_slice::<_, u8>(&tsplat.points)),
+        ("alpha_sigma", bytemuck::cast_slice(&tsplat.alpha_sigma)),
+        ("sh", bytemuck::cast_slice(&tsplat.sh)),
+    ] {
+        let array = Uint8Array::from(bytes);
+        transfer.push(&array.buffer());
+        set(&msg, key, array);
+    }
+    set(&msg, "version", tsplat.format.version);
+    set(&msg, "full_sh", tsplat.format.full_sh);
+    set(&msg, "y_up", tsplat.format.y_up);
+    if let Some(up) = tsplat.format.up {
+        set(&msg, "up", Float32Array::from(up.as_slice()));
+    }
+    set(&msg, "incomplete", tsplat.incomplete);
+    let (min, max) = tsplat.bounds;
+    set(
+        &msg,
+        "bounds",
+        Float32Array::from([min.as_slice(), max.as_slice()].concat().as_slice()),
+    );
+    (msg, transfer)
+}
+
+fn pod_vec<T: bytemuck::Pod>(array: &JsValue) -> Vec<T> {
+    let array = Uint8Array::new(array);
+    let mut values = vec![T::zeroed(); array.length() as usize / std::mem::size_of::<T>()];
+    array.copy_to(bytemuck::cast_slice_mut(&mut values));
+    values
+}
+
+fn decode_tsplat(msg: &JsValue) -> TSplat {
+    let up = get(msg, "up");
+    let bounds = Float32Array::new(&get(msg, "bounds")).to_vec();
+    TSplat {
+        points: pod_vec(&get(msg, "points")),
+        alpha_sigma: pod_vec(&get(msg, "alpha_sigma")),
+        sh: pod_vec(&get(msg, "sh")),
+        format: Format {
+            version: get(msg, "version").as_f64().unwrap_or(0.0) as u8,
+            full_sh: get(msg, "full_sh").is_truthy(),
+            y_up: get(msg, "y_up").is_truthy(),
+            up: (!up.is_undefined())
+                .then(|| Vec3f::from_column_slice(&Float32Array::new(&up).to_vec())),
+        },
+        incomplete: get(msg, "incomplete").is_truthy(),
+        bounds: (
+            Vec3f::from_column_slice(&bounds[..3]),
+            Vec3f::from_column_slice(&bounds[3..]),
+        ),
+    }
+}
+
+async fn forward_progress(pbar: ProgressBar, done: &Cell<bool>) {
+    while !done.get() {
+        let msg = message("progress");
+        set(&msg, "progress", pbar.get_progress());
+        set(&msg, "status", pbar.get_status());
+        if let Some((bytes_done, bytes_total)) = pbar.get_bytes() {
+            set(&msg, "bytes_done", bytes_done as f64);
+            set(&msg, "bytes_total", bytes_total as f64);
+        }
+        post(&msg, None);
+        yield_async(PROGRESS_INTERVAL_MS).await;
+    }
+}
+
+async fn parse<R: AsyncRead + Unpin>(reader: R, filter_degenerate: bool) {
+    let pbar = make_progress_bar();
+    let done = Cell::new(false);
+    let read = async {
+        let result = load::read_tsplat(reader, pbar.clone(), filter_degenerate, |preview| {
+            let (msg, transfer) = encode_tsplat("preview", &preview);
+            post(&msg, Some(&transfer));
+        })
+        .await;
+        done.set(true);
+        result
+    };
+    let ((), result) = futures::future::join(forward_progress(pbar.clone(), &done), read).await;
+    match result {
+        Ok(tsplat) => {
+            let (msg, transfer) = encode_tsplat("done", &tsplat);
+            post(&msg, Some(&transfer));
+        }
+        Err(err) => {
+            let msg = message("error");
+            set(&msg, "message", err);
+            post(&msg, None);
+        }
+    }
+}
+
+// Entry point of the parser worker, which receives the scene bytes in "chunk" messages.
+#[wasm_bindgen]
+pub fn parse_worker_main() {
+    console_error_panic_hook::set_once();
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let sender = RefCell::new(None);
+    let onmessage = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+        let data = event.data();
+        match get(&data, "type").as_string().as_deref() {
+            Some("start") => {
+                let (tx, rx) = mpsc::unbounded::<std::io::Result<Vec<u8>>>();
+                *sender.borrow_mut() = Some(tx);
+                let filter_degenerate = get(&data, "filter_degenerate").is_truthy();
+                wasm_bindgen_futures::spawn_local(parse(rx.into_async_read(), filter_degenerate));
+            }
+            Some("chunk") => {
+                if let Some(tx) = &*sender.borrow() {
+                    let _ = tx.unbounded_send(Ok(Uint8Array::new(&get(&data, "bytes")).to_vec()));
+                }
+            }
+            Some("fail") => {
+                if let Some(tx) = sender.borrow_mut().take() {
+                    let err = get(&data, "message").as_string().unwrap_or_default();
+                    let _ = tx.unbounded_send(Err(std::io::Error::other(err)));
+                }
+            }
+            // Dropping the sender ends the stream.
+            Some("end") => drop(sender.borrow_mut().take()),
+            _ => {}
+        }
+    });
+    scope.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    onmessage.forget();
+    // Messages sent before the handler exists are dropped, so the main thread waits for this.
+    post(&message("ready"), None);
+}
+
+struct ParserWorker {
+    worker: Worker,
+    url: String,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+    _onerror: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+impl ParserWorker {
+    fn spawn(tx: mpsc::UnboundedSender<JsValue>) -> Option<Self> {
+        let source = format!(
+            "import init, {{ parse_worker_main }} from {:?};\nawait init();\nparse_worker_main();\n",
+            bindings_url()
+        );
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("text/javascript");
+        let blob =
+            web_sys::Blob::new_with_str_sequence_and_options(&Array::of1(&source.into()), &options)
+                .ok()?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).ok()?;
+        let options = web_sys::WorkerOptions::new();
+        options.set_type(web_sys::WorkerType::Module);
+        let Ok(worker) = Worker::new_with_options(&url, &options) else {
+            let _ = web_sys::Url::revoke_object_url(&url);
+            return None;
+        };
+
+        let error_tx = tx.clone();
+        let onmessage = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let _ = tx.unbounded_send(event.data());
+        });
+        // Also fires when the module fails to load, for example under a CSP without blob: workers.
+        let onerror = Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
+            let msg = message("error");
+            set(&msg, "message", "the scene parser worker failed");
+            let _ = error_tx.unbounded_send(msg.into());
+        });
+        worker.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        worker.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+        Some(Self {
+            worker,
+            url,
+            _onmessage: onmessage,
+            _onerror: onerror,
+        })
+    }
+}
+
+impl Drop for ParserWorker {
+    fn drop(&mut self) {
+        self.worker.set_onmessage(None);
+        self.worker.set_onerror(None);
+        self.worker.terminate();
+        let _ = web_sys::Url::revoke_object_url(&self.url);
+    }
+}
+
+// Same contract as load::read_tsplat, but parses and sorts in a web worker so the main thread
+// keeps rendering. Falls back to parsing here when the worker can't start.
+pub async fn read_tsplat<S, F>(
+    byte_stream: S,
+    pbar: ProgressBar,
+    filter_degenerate: bool,
+    on_preview: F,
+) -> Result<TSplat, String>
+where
+    S: AsyncRead + Unpin,
+    F: FnOnce(TSplat),
+{
+    let (tx, mut rx) = mpsc::unbounded::<JsValue>();
+    let Some(parser) = ParserWorker::spawn(tx) else {
+        return load::read_tsplat(byte_stream, pbar, filter_degenerate, on_preview).await;
+    };
+    let ready = rx.next().await;
+    if ready
+        .and_then(|msg| get(&msg, "type").as_string())
+        .as_deref()
+        != Some("ready")
+    {
+        web_sys::console::warn_1(
+            &"scene parser worker unavailable, parsing on the main thread".into(),
+        );
+        return load::read_tsplat(byte_stream, pbar, filter_degenerate, on_preview).await;
+    }
+
+    let start = message("start");
+    set(&start, "filter_degenerate", filter_degenerate);
+    let _ = parser.worker.post_message(&start);
+
+    let pump = async {
+        let mut byte_stream = byte_stream;
+        let mut chunk = vec![0u8; CHUNK_BYTES];
+        loop {
+            match byte_stream.read(&mut chunk).await {
+                Ok(0) => {
+                    let _ = parser.worker.post_message(&message("end"));
+                    break;
+                }
+                Ok(read) => {
+                    let array = Uint8Array::from(&chunk[..read]);
+                    let msg = message("chunk");
+                    set(&msg, "bytes", &array);
+                    let _ = parser
+                        .worker
+                        .post_message_with_transfer(&msg, &Array::of1(&array.buffer()));
+                }
+                Err(err) => {
+                    let msg = message("fail");
+                    set(&msg, "message", err.to_string());
+                    let _ = parser.worker.post_message(&msg);
+                    break;
+                }
+            }
+        }
+    };
+    let receive = async {
+        let mut on_preview = Some(on_preview);
+        let mut bytes_total = None;
+        loop {
+            let Some(msg) = rx.next().await else {
+                break Err("the scene parser worker stopped unexpectedly".to_string());
+            };
+            match get(&msg, "type").as_string().as_deref() {
+                Some("progress") => {
+                    pbar.update_progress_sync(get(&msg, "progress").as_f64().unwrap_or(0.0) as f32);
+                    pbar.update_status_sync(get(&msg, "status").as_string().unwrap_or_default());
+                    // The preview and the full set are separate transfers with their own totals.
+                    let total = get(&msg, "bytes_total").as_f64().map(|total| total as u64);
+                    if total != bytes_total {
+                        match total {
+                            Some(total) => pbar.start_bytes(total),
+                            None => pbar.finish_bytes(),
+                        }
+                        bytes_total = total;
+                    }
+                    if total.is_some() {
+                        pbar.update_bytes(get(&msg, "bytes_done").as_f64().unwrap_or(0.0) as u64);
+                    }
+                }
+                Some("preview") => {
+                    if let Some(on_preview) = on_preview.take() {
+                        on_preview(decode_tsplat(&msg));
+                    }
+                }
+                Some("done") => break Ok(decode_tsplat(&msg)),
+                Some("error") => {
+                    break Err(get(&msg, "message")
+                        .as_string()
+                        .unwrap_or_else(|| "could not parse scene".to_string()));
+                }
+                _ => {}
+            }
+        }
+    };
+    // Once the worker answers, the pump is dropped with the stream, which cancels the download
+    // rather than leaving it to run after a parse error.
+    let result = match futures::future::select(Box::pin(pump), Box::pin(receive)).await {
+        Either::Left(((), receive)) => receive.await,
+        Either::Right((result, pump)) => {
+            drop(pump);
+            result
+        }
+    };
+    pbar.finish_bytes();
+    result
+}